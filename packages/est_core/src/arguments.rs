@@ -0,0 +1,160 @@
+//! Named arguments taken from the segments of a mention after the engine id.
+//!
+//! An engine declares its parameters in order, so that `@docs.serde.1.0` binds `serde` and `1.0`
+//!   to the parameters of `docs`, which are `crate` and a rest parameter `version`.
+//! - A parameter without a default is required, and it cannot follow an optional one.
+//! - The last parameter may be `rest`, which takes all the remaining segments joined with `.`.
+//!
+//! An engine without parameters accepts any number of segments, and ignores them.
+use crate::AcceptanceErr;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The separator used to join the segments taken by a `rest` parameter.
+const REST_SEP: &str = ".";
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Param {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub rest: bool,
+}
+
+/// Validated parameters of an engine, see the [module documentation](self).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Params {
+    params: Vec<Param>,
+}
+
+/// Arguments bound to the parameters, by name.
+pub type Arguments<'p> = BTreeMap<&'p str, String>;
+
+impl TryFrom<Vec<Param>> for Params {
+    type Error = String;
+
+    fn try_from(params: Vec<Param>) -> Result<Self, Self::Error> {
+        let mut optional = false;
+        for (index, param) in params.iter().enumerate() {
+            if params[..index].iter().any(|p| p.name == param.name) {
+                return Err(format!("parameter `{}` is declared twice", param.name));
+            }
+            if param.rest && index + 1 != params.len() {
+                return Err(format!("only the last parameter may be rest, not `{}`", param.name));
+            }
+            if optional && param.default.is_none() {
+                return Err(format!(
+                    "required parameter `{}` follows an optional one",
+                    param.name
+                ));
+            }
+            optional |= param.default.is_some();
+        }
+        Ok(Self { params })
+    }
+}
+
+impl Params {
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.params.iter().any(|param| param.name == name)
+    }
+
+    /// Describe how many segments the parameters take.
+    fn arity(&self) -> String {
+        let required = self.params.iter().filter(|p| p.default.is_none()).count();
+        match self.params.last() {
+            Some(param) if param.rest => format!("at least {required}"),
+            _ if required == self.params.len() => required.to_string(),
+            _ => format!("{required} to {}", self.params.len()),
+        }
+    }
+
+    /// Bind the segments of a mention after the engine id to the parameters.
+    pub fn bind(&self, id: &str, tail: &[String]) -> Result<Arguments<'_>, AcceptanceErr> {
+        let mut arguments = Arguments::new();
+        if self.is_empty() {
+            return Ok(arguments);
+        }
+
+        let bad_arity = || AcceptanceErr::BadArguments {
+            id: id.to_string(),
+            reason: format!("expected {} arguments, got {}", self.arity(), tail.len()),
+        };
+
+        let mut segments = tail.iter();
+        for param in &self.params {
+            let value = if param.rest && segments.len() > 0 {
+                Some(segments.by_ref().map(String::as_str).collect::<Vec<_>>().join(REST_SEP))
+            } else {
+                segments.next().cloned()
+            };
+            let value = value.or_else(|| param.default.clone()).ok_or_else(bad_arity)?;
+            arguments.insert(&param.name, value);
+        }
+
+        if segments.len() > 0 {
+            return Err(bad_arity());
+        }
+        Ok(arguments)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Param, Params};
+
+    fn param(name: &str, default: Option<&str>, rest: bool) -> Param {
+        Param {
+            name: name.to_string(),
+            default: default.map(String::from),
+            rest,
+        }
+    }
+
+    fn segments(s: &str) -> Vec<String> {
+        s.split('.').filter(|s| !s.is_empty()).map(String::from).collect()
+    }
+
+    #[test]
+    fn test_bind() {
+        let params = Params::try_from(vec![
+            param("crate", None, false),
+            param("version", Some("latest"), false),
+        ])
+        .unwrap();
+        let bind = |tail: &str| {
+            params
+                .bind("docs", &segments(tail))
+                .map(|args| args.into_iter().map(|(k, v)| format!("{k}={v}")).collect::<Vec<_>>())
+        };
+        assert_eq!(bind("serde.1").unwrap(), vec!["crate=serde", "version=1"]);
+        assert_eq!(bind("serde").unwrap(), vec!["crate=serde", "version=latest"]);
+        let err = bind("").unwrap_err().to_string();
+        assert!(err.ends_with("expected 1 to 2 arguments, got 0."), "{err}");
+        assert!(bind("serde.1.0").is_err());
+
+        let params = Params::try_from(vec![param("owner", None, false), param("path", None, true)]).unwrap();
+        let args = params.bind("gh", &segments("rust-lang.rust.src.lib")).unwrap();
+        assert_eq!(args["owner"], "rust-lang");
+        assert_eq!(args["path"], "rust.src.lib");
+        assert!(params.bind("gh", &segments("rust-lang")).is_err());
+
+        assert!(Params::default().bind("any", &segments("a.b.c")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_validate() {
+        for params in [
+            vec![param("a", Some("x"), false), param("b", None, false)],
+            vec![param("a", None, true), param("b", None, false)],
+            vec![param("a", None, false), param("a", None, false)],
+        ] {
+            assert!(Params::try_from(params.clone()).is_err(), "{params:?}");
+        }
+    }
+}
@@ -1,25 +1,90 @@
 //! Declarative interface for configuring est cores.
 
+use crate::engine::EngineNode;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Compose {
     #[serde(default)]
     default: Option<String>,
+    /// Sigils used when parsing queries for the instance.
+    #[serde(default)]
+    parse: crate::query::ParseOptions,
+    /// How mentions that match no engine id exactly are resolved.
+    #[serde(default)]
+    resolve: crate::engine::ResolveOptions,
     engines: Vec<crate::engine::compose::Engine>,
+    /// Collections to import more engines from.
+    #[serde(default)]
+    import: crate::import::ImportOptions,
 }
 
-impl From<Compose> for crate::Instance {
-    fn from(value: Compose) -> Self {
-        let mut engine_registry: crate::engine::EngineRegistry =
-            value.engines.into_iter().collect();
+/// Builds an engine of a custom type from its id and the rest of its fields.
+pub type EngineFactory =
+    Box<dyn Fn(String, serde_json::Value) -> Result<EngineNode, String> + Send + Sync>;
+
+/// Factories of engines by their `type`, for engines defined outside of this crate.
+#[derive(Default)]
+pub struct EngineFactories {
+    factories: HashMap<String, EngineFactory>,
+}
+
+impl EngineFactories {
+    /// Register the factory for engines of the `type`, replacing any previous one.
+    pub fn register(
+        &mut self,
+        kind: impl Into<String>,
+        factory: impl Fn(String, serde_json::Value) -> Result<EngineNode, String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.factories.insert(kind.into(), Box::new(factory));
+        self
+    }
+
+    pub(crate) fn build(
+        &self,
+        kind: &str,
+        identifier: String,
+        config: serde_json::Value,
+    ) -> Result<EngineNode, String> {
+        let factory = self
+            .factories
+            .get(kind)
+            .ok_or_else(|| format!("unknown engine type `{}`", kind))?;
+        factory(identifier, config)
+    }
+}
+
+impl Compose {
+    /// Build an instance, with engines of custom types built by the factories.
+    pub fn build(self, factories: &EngineFactories) -> crate::Instance {
+        let mut engine_registry = crate::engine::EngineRegistry::build(self.engines, factories);
+        let imported = self
+            .import
+            .load()
+            .unwrap_or_else(|err| panic!("Cannot import engines: {}", err));
+        engine_registry.import(imported.engines, factories);
 
-        if let Some(default) = value.default {
+        if let Some(default) = self.default {
             engine_registry
                 .alias("", &default)
                 .expect("A default engine is already set by not appointing an id.");
         }
 
-        Self { engine_registry }
+        self.parse
+            .validate()
+            .unwrap_or_else(|err| panic!("Invalid parse options: {}", err));
+
+        crate::Instance {
+            engine_registry,
+            parse_options: self.parse,
+            resolve_options: self.resolve,
+        }
+    }
+}
+
+impl From<Compose> for crate::Instance {
+    fn from(value: Compose) -> Self {
+        value.build(&EngineFactories::default())
     }
 }
@@ -1,16 +1,47 @@
 use crate::{AcceptanceErr, Instance, Query, Reaction};
 use cloze::ClozeScoped;
 use futures::{future::BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
 use slotmap::{new_key_type, SlotMap};
-use std::{collections::HashMap, future::Future};
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+};
 use thiserror::Error;
 
 pub mod alias;
+#[cfg(feature = "bookmarks")]
+pub mod bookmarks;
+pub mod calc;
+pub mod chain;
 pub mod cloze;
+pub mod direct;
+#[cfg(feature = "lang")]
+pub mod lang;
 pub mod namespace;
 pub mod ortho;
+pub mod pattern;
+#[cfg(feature = "script")]
+pub mod script;
+#[cfg(feature = "switch")]
+pub mod switch;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
-use self::{alias::Alias, cloze::Cloze, namespace::Namespace, ortho::Ortho};
+use self::{
+    alias::Alias, calc::Calc, chain::Chain, cloze::Cloze, direct::Direct, namespace::Namespace, ortho::Ortho,
+    pattern::Pattern,
+};
+#[cfg(feature = "bookmarks")]
+use self::bookmarks::Bookmarks;
+#[cfg(feature = "lang")]
+use self::lang::Lang;
+#[cfg(feature = "script")]
+use self::script::Script;
+#[cfg(feature = "switch")]
+use self::switch::Switch;
+#[cfg(feature = "wasm")]
+use self::wasm::Wasm;
 
 pub trait Engine {
     /// Get the identifier of the engine.
@@ -31,25 +62,93 @@ pub trait Engine {
     }
 }
 
+/// An object-safe counterpart of [`Engine`], so that engines defined outside of this crate
+///   can be registered as [`EngineNode::Custom`].
+/// It is implemented for every [`Engine`] that can be shared between threads.
+pub trait DynEngine: Send + Sync {
+    fn dyn_identifier(&self) -> &str;
+
+    fn dyn_react<'e, 'q: 'e, 'i: 'e>(
+        &'e self,
+        query: &'q Query,
+        instance: &'i Instance,
+    ) -> BoxFuture<'e, Reaction>;
+
+    fn dyn_accept(&self, query: &Query, instance: &Instance) -> Result<(), AcceptanceErr>;
+}
+
+impl<E: Engine + Send + Sync> DynEngine for E {
+    fn dyn_identifier(&self) -> &str {
+        self.identifier()
+    }
+
+    fn dyn_react<'e, 'q: 'e, 'i: 'e>(
+        &'e self,
+        query: &'q Query,
+        instance: &'i Instance,
+    ) -> BoxFuture<'e, Reaction> {
+        self.react(query, instance).boxed()
+    }
+
+    fn dyn_accept(&self, query: &Query, instance: &Instance) -> Result<(), AcceptanceErr> {
+        self.accept(query, instance)
+    }
+}
+
 new_key_type! { pub(crate) struct EngineKey; }
 
 #[non_exhaustive]
 pub enum EngineNode {
     Alias(Alias),
+    #[cfg(feature = "bookmarks")]
+    Bookmarks(Bookmarks),
+    Calc(Calc),
+    Chain(Chain),
     Namespace(Namespace),
     Cloze(Cloze),
     ClozeScoped(ClozeScoped),
+    Direct(Direct),
+    #[cfg(feature = "lang")]
+    Lang(Lang),
     Ortho(Ortho),
+    Pattern(Pattern),
+    #[cfg(feature = "script")]
+    Script(Script),
+    #[cfg(feature = "switch")]
+    Switch(Switch),
+    #[cfg(feature = "wasm")]
+    Wasm(Wasm),
+    Custom(Box<dyn DynEngine>),
 }
 
 impl EngineNode {
+    /// Wrap an engine defined outside of this crate.
+    pub fn custom(engine: impl DynEngine + 'static) -> Self {
+        Self::Custom(Box::new(engine))
+    }
+
     pub fn accept(&self, query: &Query, instance: &Instance) -> Result<(), AcceptanceErr> {
         match self {
             Self::Alias(alias) => alias.accept(query, instance),
+            #[cfg(feature = "bookmarks")]
+            Self::Bookmarks(bookmarks) => bookmarks.accept(query, instance),
+            Self::Calc(calc) => calc.accept(query, instance),
+            Self::Chain(chain) => chain.accept(query, instance),
             Self::Namespace(namespace) => namespace.accept(query, instance),
             Self::Cloze(cloze) => cloze.accept(query, instance),
             Self::ClozeScoped(cloze_scoped) => cloze_scoped.accept(query, instance),
+            Self::Direct(direct) => direct.accept(query, instance),
+            #[cfg(feature = "lang")]
+            Self::Lang(lang) => lang.accept(query, instance),
             Self::Ortho(ortho) => ortho.accept(query, instance),
+            Self::Pattern(pattern) => pattern.accept(query, instance),
+            #[cfg(feature = "script")]
+            Self::Script(script) => script.accept(query, instance),
+            #[cfg(feature = "switch")]
+            Self::Switch(switch) => switch.accept(query, instance),
+            #[cfg(feature = "wasm")]
+            Self::Wasm(wasm) => wasm.accept(query, instance),
+            Self::Custom(custom) => custom.dyn_accept(query, instance),
         }
     }
 
@@ -60,18 +159,63 @@ impl EngineNode {
     ) -> BoxFuture<'e, Reaction> {
         match self {
             Self::Alias(alias) => alias.react(query, instance).boxed(),
+            #[cfg(feature = "bookmarks")]
+            Self::Bookmarks(bookmarks) => bookmarks.react(query, instance).boxed(),
+            Self::Calc(calc) => calc.react(query, instance).boxed(),
+            Self::Chain(chain) => chain.react(query, instance).boxed(),
             Self::Namespace(namespace) => namespace.react(query, instance).boxed(),
             Self::Cloze(cloze) => cloze.react(query, instance).boxed(),
             Self::ClozeScoped(cloze_scoped) => cloze_scoped.react(query, instance).boxed(),
+            Self::Direct(direct) => direct.react(query, instance).boxed(),
+            #[cfg(feature = "lang")]
+            Self::Lang(lang) => lang.react(query, instance).boxed(),
             Self::Ortho(ortho) => ortho.react(query, instance).boxed(),
+            Self::Pattern(pattern) => pattern.react(query, instance).boxed(),
+            #[cfg(feature = "script")]
+            Self::Script(script) => script.react(query, instance).boxed(),
+            #[cfg(feature = "switch")]
+            Self::Switch(switch) => switch.react(query, instance).boxed(),
+            #[cfg(feature = "wasm")]
+            Self::Wasm(wasm) => wasm.react(query, instance).boxed(),
+            Self::Custom(custom) => custom.dyn_react(query, instance),
+        }
+    }
+}
+
+/// How a mention that matches no engine id exactly is resolved.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResolveOptions {
+    /// Resolve a mention to the engine it nearly matches, if no other engine is as close.
+    pub autocorrect: bool,
+    /// The maximum edit distance of suggested and auto-corrected ids.
+    pub max_distance: usize,
+    /// Resolve a mention to the engine whose ids are the only ones starting with it,
+    ///   e.g. `@wikt` to `wiktionary`.
+    pub prefix: bool,
+}
+
+impl Default for ResolveOptions {
+    fn default() -> Self {
+        Self {
+            autocorrect: false,
+            max_distance: 2,
+            prefix: false,
         }
     }
 }
 
+/// The maximum number of ids suggested for an unknown mention.
+const MAX_SUGGESTIONS: usize = 5;
+
 pub(crate) struct EngineRegistry {
     engines: SlotMap<EngineKey, EngineNode>,
-    ids: HashMap<String, EngineKey>,
+    /// Ids and aliases of engines.
+    /// Being ordered, it also serves as the prefix index of the ids.
+    ids: BTreeMap<String, EngineKey>,
     description: HashMap<EngineKey, Option<String>>,
+    category: HashMap<EngineKey, String>,
+    icon: HashMap<EngineKey, String>,
 }
 
 #[derive(Debug, Error)]
@@ -96,15 +240,102 @@ impl EngineRegistry {
         self.ids.get(id).and_then(|key| {
             self.description
                 .get(key)
-                .map(Option::as_deref)
-                .flatten()
+                .and_then(Option::as_deref)
         })
     }
 
+    pub(crate) fn category(&self, id: impl AsRef<str>) -> Option<&str> {
+        let id = id.as_ref();
+        self.ids
+            .get(id)
+            .and_then(|key| self.category.get(key))
+            .map(String::as_str)
+    }
+
+    pub(crate) fn icon(&self, id: impl AsRef<str>) -> Option<&str> {
+        let id = id.as_ref();
+        self.ids
+            .get(id)
+            .and_then(|key| self.icon.get(key))
+            .map(String::as_str)
+    }
+
     pub(crate) fn iter_ids(&self) -> impl Iterator<Item = &String> {
         self.ids.keys()
     }
 
+    /// Ids starting with `prefix`, in order.
+    fn ids_with_prefix<'r, 'p>(
+        &'r self,
+        prefix: &'p str,
+    ) -> impl Iterator<Item = (&'r String, &'r EngineKey)> + use<'r, 'p> {
+        self.ids
+            .range::<str, _>((std::ops::Bound::Included(prefix), std::ops::Bound::Unbounded))
+            .take_while(move |(id, _)| id.starts_with(prefix))
+    }
+
+    /// Ids within the edit distance of `id`, nearest first.
+    fn similar_ids(&self, id: &str, max_distance: usize) -> Vec<(usize, &str)> {
+        let len = id.chars().count();
+        let mut similar: Vec<(usize, &str)> = self
+            .ids
+            .keys()
+            .map(|candidate| (strsim::osa_distance(id, candidate), candidate.as_str()))
+            .filter(|(distance, _)| *distance <= max_distance && *distance < len)
+            .collect();
+        similar.sort_unstable();
+        similar
+    }
+
+    /// Find the engine for a mentioned id, and the id it is registered under.
+    /// Unknown ids are auto-corrected or reported with suggestions as configured.
+    pub(crate) fn resolve<'r>(
+        &'r self,
+        id: &str,
+        options: &ResolveOptions,
+    ) -> Result<(&'r str, &'r EngineNode), AcceptanceErr> {
+        if let Some((id, key)) = self.ids.get_key_value(id) {
+            return Ok((id, &self.engines[*key]));
+        }
+
+        if options.prefix && !id.is_empty() {
+            let candidates: Vec<(&String, &EngineKey)> = self.ids_with_prefix(id).collect();
+            if let Some((first, key)) = candidates.first() {
+                if candidates.iter().all(|(_, k)| k == key) {
+                    return Ok((first, &self.engines[**key]));
+                }
+                return Err(AcceptanceErr::AmbiguousEngine {
+                    id: id.to_string(),
+                    candidates: candidates.into_iter().map(|(id, _)| id.clone()).collect(),
+                });
+            }
+        }
+
+        let similar = self.similar_ids(id, options.max_distance);
+
+        if options.autocorrect
+            && let Some((nearest, nearest_id)) = similar.first()
+        {
+            let key = self.ids[*nearest_id];
+            let is_unambiguous = similar
+                .iter()
+                .take_while(|(distance, _)| distance == nearest)
+                .all(|(_, id)| self.ids[*id] == key);
+            if is_unambiguous {
+                return Ok((nearest_id, &self.engines[key]));
+            }
+        }
+
+        Err(AcceptanceErr::UnknownEngine {
+            id: id.to_string(),
+            suggestions: similar
+                .into_iter()
+                .take(MAX_SUGGESTIONS)
+                .map(|(_, id)| id.to_string())
+                .collect(),
+        })
+    }
+
     pub(crate) fn alias(
         &mut self,
         id: impl AsRef<str>,
@@ -126,13 +357,25 @@ impl EngineRegistry {
 
 pub(crate) mod compose {
     use super::{
-        alias::compose::Alias, cloze::compose::Cloze, namespace::compose::Namespace,
-        ortho::compose::Ortho, EngineRegistry,
+        alias::compose::Alias, calc::compose::Calc, chain::compose::Chain, cloze::compose::Cloze,
+        direct::compose::Direct, namespace::compose::Namespace, ortho::compose::Ortho, pattern::compose::Pattern,
+        EngineRegistry,
     };
-    use serde::{Deserialize, Serialize};
+    #[cfg(feature = "bookmarks")]
+    use super::bookmarks::compose::Bookmarks;
+    #[cfg(feature = "lang")]
+    use super::lang::compose::Lang;
+    #[cfg(feature = "script")]
+    use super::script::compose::Script;
+    #[cfg(feature = "switch")]
+    use super::switch::compose::Switch;
+    #[cfg(feature = "wasm")]
+    use super::wasm::compose::Wasm;
+    use crate::compose::EngineFactories;
+    use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
 
     use slotmap::SlotMap;
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap};
 
     #[derive(Debug, Deserialize, Serialize)]
     #[serde(untagged)]
@@ -152,69 +395,201 @@ pub(crate) mod compose {
         #[serde(default)]
         pub(crate) id: String,
         #[serde(flatten)]
-        pub(crate) engine: EngineType,
+        pub(crate) engine: EngineSpec,
         #[serde(default)]
         pub(crate) shorthand: Shorthand,
         pub(crate) description: Option<String>,
+        /// A free-form category, such as those of imported bangs.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub(crate) category: Option<String>,
+        /// The url of an icon, such as the favicon of the site the engine searches.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub(crate) icon: Option<String>,
     }
 
-    #[non_exhaustive]
-    #[derive(Debug, Deserialize, Serialize)]
-    #[serde(tag = "type", rename_all = "kebab-case")]
-    pub enum EngineType {
-        Alias(Alias),
-        Cloze(Cloze),
-        Namespace(Namespace),
-        Ortho(Ortho),
+    /// Declare [`EngineType`] along with the `type` of each variant,
+    ///   so that telling builtin types from custom ones and building them cannot miss a variant.
+    /// A variant left out by its feature is not builtin, and its type has to be registered as a custom one.
+    macro_rules! engine_types {
+        ($($(#[$attr:meta])* $variant:ident($compose:ident) = $kind:literal,)*) => {
+            #[non_exhaustive]
+            #[derive(Debug, Deserialize, Serialize)]
+            #[serde(tag = "type")]
+            pub enum EngineType {
+                $($(#[$attr])* #[serde(rename = $kind)] $variant($compose),)*
+            }
+
+            /// Whether the `type` is that of a variant of [`EngineType`], any other type being a custom one.
+            fn is_builtin(kind: &str) -> bool {
+                match kind {
+                    $($(#[$attr])* $kind => true,)*
+                    _ => false,
+                }
+            }
+
+            impl EngineType {
+                fn build(self, identifier: String) -> super::EngineNode {
+                    match self {
+                        $($(#[$attr])* Self::$variant(engine) => engine.build(identifier),)*
+                    }
+                }
+            }
+        };
+    }
+
+    engine_types! {
+        Alias(Alias) = "alias",
+        #[cfg(feature = "bookmarks")]
+        Bookmarks(Bookmarks) = "bookmarks",
+        Calc(Calc) = "calc",
+        Chain(Chain) = "chain",
+        Cloze(Cloze) = "cloze",
+        Direct(Direct) = "direct",
+        #[cfg(feature = "lang")]
+        Lang(Lang) = "lang",
+        Namespace(Namespace) = "namespace",
+        Ortho(Ortho) = "ortho",
+        Pattern(Pattern) = "pattern",
+        #[cfg(feature = "script")]
+        Script(Script) = "script",
+        #[cfg(feature = "switch")]
+        Switch(Switch) = "switch",
+        #[cfg(feature = "wasm")]
+        Wasm(Wasm) = "wasm",
+    }
+
+    /// An engine of a `type` registered in [`EngineFactories`] by the embedding application.
+    #[derive(Debug, Serialize)]
+    pub struct CustomEngine {
+        #[serde(rename = "type")]
+        pub(crate) kind: String,
+        /// The rest of the fields of the engine.
+        #[serde(flatten)]
+        pub(crate) config: serde_json::Map<String, serde_json::Value>,
+    }
+
+    #[derive(Debug, Serialize)]
+    #[serde(untagged)]
+    pub enum EngineSpec {
+        Builtin(EngineType),
+        Custom(CustomEngine),
+    }
+
+    impl<'de> Deserialize<'de> for EngineSpec {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let mut config = serde_json::Map::deserialize(deserializer)?;
+            let kind = match config.get("type") {
+                Some(serde_json::Value::String(kind)) => kind.clone(),
+                Some(_) => return Err(D::Error::custom("`type` of an engine must be a string")),
+                None => return Err(D::Error::missing_field("type")),
+            };
+
+            if is_builtin(&kind) {
+                EngineType::deserialize(serde_json::Value::Object(config))
+                    .map(Self::Builtin)
+                    .map_err(D::Error::custom)
+            } else {
+                config.remove("type");
+                Ok(Self::Custom(CustomEngine { kind, config }))
+            }
+        }
     }
 
     impl Engine {
-        fn build(self, registry: &mut EngineRegistry) {
+        /// Build the engine and register it under its id and shorthands.
+        /// An imported engine does not take ids that are already registered,
+        ///   and is skipped if it is left with none of its shorthands.
+        fn build(self, registry: &mut EngineRegistry, factories: &EngineFactories, imported: bool) {
             let Engine {
                 engine,
                 id,
                 shorthand,
                 description,
+                category,
+                icon,
             } = self;
 
+            let mut shorthand = match shorthand {
+                Shorthand::Single(s) => vec![s],
+                Shorthand::Multiple(shorthand) => shorthand,
+            };
+            if imported {
+                if registry.ids.contains_key(&id) {
+                    return;
+                }
+                let has_shorthand = !shorthand.is_empty();
+                shorthand.retain(|s| !registry.ids.contains_key(s));
+                if has_shorthand && shorthand.is_empty() {
+                    return;
+                }
+            }
+
             let identifier = id.clone();
             let engine = match engine {
-                EngineType::Alias(alias) => alias.build(identifier),
-                EngineType::Cloze(cloze) => cloze.build(identifier),
-                EngineType::Namespace(namespace) => namespace.build(identifier),
-                EngineType::Ortho(ortho) => ortho.build(identifier),
+                EngineSpec::Builtin(engine) => engine.build(identifier),
+                EngineSpec::Custom(CustomEngine { kind, config }) => factories
+                    .build(&kind, identifier, config.into())
+                    .unwrap_or_else(|err| panic!("Cannot build engine `{}`: {}", id, err)),
             };
 
             let key = registry.engines.insert(engine);
             registry.description.insert(key, description);
+            if let Some(category) = category {
+                registry.category.insert(key, category);
+            }
+            if let Some(icon) = icon {
+                registry.icon.insert(key, icon);
+            }
             registry.ids.insert(id, key);
-
-            match shorthand {
-                Shorthand::Single(s) => {
-                    registry.ids.insert(s, key);
-                }
-                Shorthand::Multiple(shorthand) => {
-                    for s in shorthand.into_iter() {
-                        registry.ids.insert(s, key);
-                    }
-                }
+            for s in shorthand {
+                registry.ids.insert(s, key);
             }
         }
     }
 
-    impl FromIterator<Engine> for EngineRegistry {
-        fn from_iter<T: IntoIterator<Item = Engine>>(iter: T) -> Self {
+    impl EngineRegistry {
+        /// Build the engines, those of custom types by the factories registered for them.
+        pub(crate) fn build(engines: impl IntoIterator<Item = Engine>, factories: &EngineFactories) -> Self {
             let mut registry = EngineRegistry {
                 engines: SlotMap::with_key(),
-                ids: HashMap::new(),
+                ids: BTreeMap::new(),
                 description: HashMap::new(),
+                category: HashMap::new(),
+                icon: HashMap::new(),
             };
 
-            for e in iter {
-                e.build(&mut registry);
+            for e in engines {
+                e.build(&mut registry, factories, false);
             }
 
             registry
         }
+
+        /// Add imported engines, which give way to the engines already registered.
+        pub(crate) fn import(&mut self, engines: impl IntoIterator<Item = Engine>, factories: &EngineFactories) {
+            for e in engines {
+                e.build(self, factories, true);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::{EngineSpec, EngineType};
+
+        #[test]
+        fn test_engine_spec() {
+            let spec = |toml: &str| toml::from_str::<EngineSpec>(toml);
+            assert!(matches!(spec(r#"type = "calc""#), Ok(EngineSpec::Builtin(EngineType::Calc(_)))));
+            assert!(matches!(
+                spec(r#"type = "cloze"
+                    template = "https://example.com/?q={}""#),
+                Ok(EngineSpec::Builtin(EngineType::Cloze(_)))
+            ));
+            assert!(matches!(spec(r#"type = "weather""#), Ok(EngineSpec::Custom(custom)) if custom.kind == "weather"));
+            // A builtin type with a bad config is an error, not a custom engine.
+            assert!(spec(r#"type = "cloze""#).is_err());
+            assert!(spec("template = 1").is_err());
+        }
     }
 }
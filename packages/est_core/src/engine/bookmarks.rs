@@ -0,0 +1,464 @@
+//! An engine that searches local bookmarks, navigating to the best hit.
+//!
+//! Bookmarks are read from Netscape bookmark files, as exported by every browser,
+//!   or from Firefox JSON backups, compressed or not.
+//! They are read once at startup, when the engine is built; there is no reload, so edits need a restart.
+//! A file that cannot be read is logged and skipped, leaving the engine without its bookmarks.
+//!
+//! Every word of the content has to match the title, the tags or the url of a bookmark,
+//!   exactly, as a prefix, as a part, or with a typo.
+//! When other hits score close to the best one, they are listed in an answer instead.
+use super::{Engine, EngineNode};
+use crate::{
+    Instance, Query, Reaction, ReactionErr,
+    import::{ImportError, decompress, read},
+    reaction::{Answer, Navigate, escape_html},
+};
+use regex::Regex;
+use serde::Deserialize;
+use std::{future::Future, path::Path, sync::LazyLock};
+use url::Url;
+
+static ANCHOR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<a\s([^>]*)>(.*?)</a\s*>").unwrap());
+static ATTRIBUTE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i)([a-z_]+)\s*=\s*"([^"]*)""#).unwrap());
+static MARKUP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+/// The weight of a match in the url, below one in the title or the tags.
+const URL_WEIGHT: f64 = 0.7;
+/// The minimum Jaro-Winkler similarity of a word with a typo.
+const FUZZY_SIMILARITY: f64 = 0.85;
+
+pub struct Bookmark {
+    title: String,
+    url: Url,
+    tags: Vec<String>,
+    title_words: Vec<String>,
+    tag_words: Vec<String>,
+    url_words: Vec<String>,
+}
+
+impl Bookmark {
+    /// Make a bookmark, unless the url is invalid or does not lead to a page, like `javascript:`.
+    fn new(title: &str, url: &str, tags: Vec<String>) -> Option<Self> {
+        let url = Url::parse(url.trim()).ok()?;
+        if matches!(url.scheme(), "javascript" | "place" | "data") {
+            return None;
+        }
+        let title = title.trim().to_string();
+        let url_words = words(&format!("{} {}", url.host_str().unwrap_or_default(), url.path()));
+        Some(Self {
+            title_words: words(&title),
+            tag_words: tags.iter().flat_map(|tag| words(tag)).collect(),
+            url_words,
+            title,
+            url,
+            tags,
+        })
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// The mean score of the query words, unless a word matches nothing.
+    fn score(&self, query: &[String]) -> Option<f64> {
+        let mut total = 0.0;
+        for word in query {
+            let score = [
+                match_word(word, &self.title_words),
+                match_word(word, &self.tag_words),
+                match_word(word, &self.url_words) * URL_WEIGHT,
+            ]
+            .into_iter()
+            .fold(0.0, f64::max);
+            if score == 0.0 {
+                return None;
+            }
+            total += score;
+        }
+        Some(total / query.len() as f64)
+    }
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// How well a word matches the best of the words of a field, from 0 to 1.
+fn match_word(word: &str, field: &[String]) -> f64 {
+    let len = word.chars().count();
+    field
+        .iter()
+        .map(|candidate| {
+            if candidate == word {
+                1.0
+            } else if candidate.starts_with(word) {
+                0.8
+            } else if len >= 3 && candidate.contains(word) {
+                0.6
+            } else if len >= 4 {
+                let similarity = strsim::jaro_winkler(word, candidate);
+                if similarity >= FUZZY_SIMILARITY { 0.7 * similarity } else { 0.0 }
+            } else {
+                0.0
+            }
+        })
+        .fold(0.0, f64::max)
+}
+
+fn unescape_html(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some('\u{a0}'),
+                entity => entity
+                    .strip_prefix('#')
+                    .and_then(|code| match code.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => code.parse().ok(),
+                    })
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match entity {
+            Some((c, end)) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+fn split_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Read the bookmarks of a Netscape bookmark file, whose `<A>` elements are the bookmarks.
+fn from_html(html: &str) -> Vec<Bookmark> {
+    ANCHOR
+        .captures_iter(html)
+        .filter_map(|anchor| {
+            let (mut url, mut tags) = (None, Vec::new());
+            for attribute in ATTRIBUTE.captures_iter(&anchor[1]) {
+                let value = unescape_html(&attribute[2]);
+                match attribute[1].to_ascii_lowercase().as_str() {
+                    "href" => url = Some(value),
+                    "tags" => tags = split_tags(&value),
+                    _ => {}
+                }
+            }
+            let title = unescape_html(&MARKUP.replace_all(&anchor[2], ""));
+            Bookmark::new(&title, &url?, tags)
+        })
+        .collect()
+}
+
+/// A node of a Firefox JSON backup, either a bookmark with a `uri` or a folder with `children`.
+#[derive(Deserialize)]
+struct Place {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    uri: Option<String>,
+    /// Comma-separated tags.
+    #[serde(default)]
+    tags: Option<String>,
+    #[serde(default)]
+    children: Vec<Place>,
+}
+
+fn from_json(json: &[u8]) -> Result<Vec<Bookmark>, ImportError> {
+    fn collect(place: Place, bookmarks: &mut Vec<Bookmark>) {
+        let tags = split_tags(place.tags.as_deref().unwrap_or_default());
+        if let Some(uri) = &place.uri
+            && let Some(bookmark) = Bookmark::new(&place.title, uri, tags)
+        {
+            bookmarks.push(bookmark);
+        }
+        for child in place.children {
+            collect(child, bookmarks);
+        }
+    }
+
+    let mut bookmarks = Vec::new();
+    collect(serde_json::from_slice(json)?, &mut bookmarks);
+    Ok(bookmarks)
+}
+
+/// Read the bookmarks of a Netscape bookmark file, or of a Firefox JSON backup, compressed or not.
+pub fn load(path: &Path) -> Result<Vec<Bookmark>, ImportError> {
+    let bytes = decompress(&read(path)?)?;
+    if bytes.trim_ascii_start().starts_with(b"{") {
+        from_json(&bytes)
+    } else {
+        Ok(from_html(&String::from_utf8_lossy(&bytes)))
+    }
+}
+
+pub struct Bookmarks {
+    identifier: String,
+    bookmarks: Vec<Bookmark>,
+    /// Hits scoring at least this ratio of the best one are close to it.
+    close_ratio: f64,
+    max_candidates: usize,
+}
+
+impl Bookmarks {
+    /// The bookmarks matching the content, best first.
+    fn search(&self, content: &str) -> Vec<(f64, &Bookmark)> {
+        let query = words(content);
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let mut hits: Vec<_> = self
+            .bookmarks
+            .iter()
+            .filter_map(|bookmark| bookmark.score(&query).map(|score| (score, bookmark)))
+            .collect();
+        hits.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        hits
+    }
+
+    /// The best hit, or the hits close to it if there are others.
+    fn candidates(&self, content: &str) -> Vec<&Bookmark> {
+        let hits = self.search(content);
+        let Some((best, _)) = hits.first() else {
+            return Vec::new();
+        };
+        let threshold = best * self.close_ratio;
+        hits.iter()
+            .take_while(|(score, _)| *score >= threshold)
+            .take(self.max_candidates)
+            .map(|(_, bookmark)| *bookmark)
+            .collect()
+    }
+}
+
+fn list(candidates: &[&Bookmark]) -> Answer {
+    let name = |bookmark: &Bookmark| match bookmark.title.as_str() {
+        "" => bookmark.url.to_string(),
+        title => title.to_string(),
+    };
+    let text = candidates
+        .iter()
+        .map(|bookmark| format!("{} <{}>", name(bookmark), bookmark.url))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let items: String = candidates
+        .iter()
+        .map(|bookmark| {
+            let url = escape_html(bookmark.url.as_str());
+            format!(r#"<li><a href="{url}">{}</a> <small>{url}</small></li>"#, escape_html(&name(bookmark)))
+        })
+        .collect();
+    Answer::new(text).with_html(format!(r#"<ul id="candidates">{items}</ul>"#))
+}
+
+impl Engine for Bookmarks {
+    fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    fn react<'e, 'q: 'e, 'i: 'e>(
+        &'e self,
+        query: &'q Query,
+        _instance: &'i Instance,
+    ) -> impl Future<Output = Reaction> + Send + 'e {
+        let reaction = match self.candidates(query.content()).as_slice() {
+            [] => Err(ReactionErr::Nothing),
+            [bookmark] => Navigate::from_str(bookmark.url.as_str(), false),
+            candidates => Ok(list(candidates).into()),
+        };
+
+        async move { reaction }
+    }
+}
+
+impl From<Bookmarks> for EngineNode {
+    fn from(bookmarks: Bookmarks) -> Self {
+        Self::Bookmarks(bookmarks)
+    }
+}
+
+pub(crate) mod compose {
+    use serde::{Deserialize, Serialize};
+    use std::{collections::HashSet, path::PathBuf};
+
+    #[derive(Deserialize, Serialize, Debug)]
+    pub(crate) struct Bookmarks {
+        /// Netscape bookmark files like `bookmarks.html`, or Firefox JSON backups,
+        ///   relative to the working directory.
+        pub paths: Vec<PathBuf>,
+        /// Hits scoring at least this ratio of the best one are listed instead of navigating, 0.8 by default.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub close_ratio: Option<f64>,
+        /// The maximum number of hits listed, 5 by default.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub max_candidates: Option<usize>,
+    }
+
+    impl Bookmarks {
+        pub(crate) fn build(self, identifier: String) -> crate::engine::EngineNode {
+            if let Some(ratio) = self.close_ratio
+                && !(0.0..=1.0).contains(&ratio)
+            {
+                panic!("Close ratio of bookmarks engine {} is not within 0 and 1: {}", identifier, ratio);
+            }
+            if self.max_candidates == Some(0) {
+                panic!("Bookmarks engine {} lists no candidates", identifier);
+            }
+
+            let mut bookmarks: Vec<super::Bookmark> = Vec::new();
+            let mut urls = HashSet::new();
+            for path in &self.paths {
+                let loaded = match super::load(path) {
+                    Ok(loaded) => loaded,
+                    Err(err) => {
+                        log::error!("Cannot load bookmarks of engine `{}` at {}: {}", identifier, path.display(), err);
+                        continue;
+                    }
+                };
+                for bookmark in loaded {
+                    if urls.insert(bookmark.url.clone()) {
+                        bookmarks.push(bookmark);
+                    }
+                }
+            }
+
+            super::Bookmarks {
+                identifier,
+                bookmarks,
+                close_ratio: self.close_ratio.unwrap_or(0.8),
+                max_candidates: self.max_candidates.unwrap_or(5),
+            }
+            .into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Bookmarks, EngineNode, from_html, from_json, unescape_html};
+
+    const HTML: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1700000000">Team</H3>
+    <DL><p>
+        <DT><A HREF="https://grafana.example.com/d/api" ADD_DATE="1700000000" TAGS="dashboard,ops">API latency</A>
+        <DT><A HREF="https://wiki.example.com/onboarding?lang=en&amp;v=2">Onboarding &amp; setup</A>
+        <DT><A HREF="https://ci.example.com/pipelines" TAGS="ci">Build pipelines</A>
+        <DT><A HREF="https://ci.example.com/releases" TAGS="ci">Release pipelines</A>
+        <DT><A HREF="javascript:alert(1)">Bookmarklet</A>
+    </DL><p>
+</DL><p>
+"#;
+
+    fn bookmarks() -> Bookmarks {
+        Bookmarks {
+            identifier: "bookmarks".to_string(),
+            bookmarks: from_html(HTML),
+            close_ratio: 0.8,
+            max_candidates: 5,
+        }
+    }
+
+    fn candidates(content: &str) -> Vec<String> {
+        let bookmarks = bookmarks();
+        bookmarks
+            .candidates(content)
+            .iter()
+            .map(|bookmark| bookmark.title().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_from_html() {
+        let bookmarks = from_html(HTML);
+        assert_eq!(bookmarks.len(), 4);
+        assert_eq!(bookmarks[0].tags(), ["dashboard", "ops"]);
+        assert_eq!(bookmarks[1].title(), "Onboarding & setup");
+        assert_eq!(bookmarks[1].url().as_str(), "https://wiki.example.com/onboarding?lang=en&v=2");
+    }
+
+    #[test]
+    fn test_from_json() {
+        let json = br#"{"title": "", "type": "text/x-moz-place-container", "children": [
+            {"title": "toolbar", "type": "text/x-moz-place-container", "children": [
+                {"title": "Rust docs", "type": "text/x-moz-place", "uri": "https://doc.rust-lang.org/std/",
+                 "tags": "rust,docs"},
+                {"title": "Recent tags", "type": "text/x-moz-place", "uri": "place:type=6&sort=14"}
+            ]},
+            {"title": "Separator", "type": "text/x-moz-place-separator"}
+        ]}"#;
+        let bookmarks = from_json(json).unwrap();
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0].title(), "Rust docs");
+        assert_eq!(bookmarks[0].tags(), ["rust", "docs"]);
+    }
+
+    #[test]
+    fn test_candidates() {
+        assert_eq!(candidates("api latency"), ["API latency"]);
+        assert_eq!(candidates("dashboard"), ["API latency"]);
+        assert_eq!(candidates("grafana"), ["API latency"]);
+        assert_eq!(candidates("onbaording"), ["Onboarding & setup"]);
+        assert_eq!(candidates("release"), ["Release pipelines"]);
+        assert_eq!(candidates("pipelines"), ["Build pipelines", "Release pipelines"]);
+        assert_eq!(candidates("ci"), ["Build pipelines", "Release pipelines"]);
+        assert!(candidates("bookmarklet").is_empty());
+        assert!(candidates("api deploy").is_empty());
+        assert!(candidates("").is_empty());
+    }
+
+    #[test]
+    fn test_missing_file() {
+        let compose = super::compose::Bookmarks {
+            paths: vec!["missing/bookmarks.html".into()],
+            close_ratio: None,
+            max_candidates: None,
+        };
+        let EngineNode::Bookmarks(bookmarks) = compose.build("bookmarks".to_string()) else {
+            panic!("Expected a bookmarks engine");
+        };
+        assert!(bookmarks.bookmarks.is_empty());
+    }
+
+    #[test]
+    fn test_unescape_html() {
+        assert_eq!(unescape_html("a &amp; b &lt;c&gt; &#39;d&#x27; &bogus; &"), "a & b <c> 'd' &bogus; &");
+    }
+}
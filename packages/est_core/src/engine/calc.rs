@@ -0,0 +1,714 @@
+//! An engine that answers arithmetic and unit conversions, like `@calc 3 ft in cm`, locally.
+//!
+//! Expressions have `+ - * / % ^`, parentheses, the constants `pi` and `e`,
+//!   functions like `sqrt(2)`, and quantities like `3 ft` or `60 km/h`.
+//! A trailing `in`, `to` or `as` converts the result to other units, which are otherwise SI ones.
+//! Temperatures in °C and °F are absolute, so they only follow a number and take no arithmetic,
+//!   as `10 °C + 5 °C` has no meaning; they can still be converted, like `10 °C in °F`.
+//! A query that is not an expression, or has no number like a unit alone, is rejected,
+//!   so that a chain can pass it on.
+use super::{Engine, EngineNode};
+use crate::{AcceptanceErr, Instance, Query, Reaction, reaction::Answer};
+use std::{f64::consts, future::Future, ops::Range};
+
+/// Exponents of length, mass, time, temperature and information.
+type Dimension = [i8; 5];
+
+const DIMENSIONLESS: Dimension = [0; 5];
+const BASE_UNITS: [&str; 5] = ["m", "kg", "s", "K", "B"];
+const LENGTH: Dimension = [1, 0, 0, 0, 0];
+const AREA: Dimension = [2, 0, 0, 0, 0];
+const VOLUME: Dimension = [3, 0, 0, 0, 0];
+const MASS: Dimension = [0, 1, 0, 0, 0];
+const TIME: Dimension = [0, 0, 1, 0, 0];
+const SPEED: Dimension = [1, 0, -1, 0, 0];
+const TEMPERATURE: Dimension = [0, 0, 0, 1, 0];
+const INFORMATION: Dimension = [0, 0, 0, 0, 1];
+
+/// The maximum nesting of parentheses and operators.
+const MAX_DEPTH: usize = 64;
+
+struct Unit {
+    names: &'static [&'static str],
+    /// The value of the unit in base units.
+    factor: f64,
+    /// Added after scaling, for temperatures other than kelvin.
+    offset: f64,
+    dimension: Dimension,
+}
+
+const fn unit(names: &'static [&'static str], factor: f64, dimension: Dimension) -> Unit {
+    Unit {
+        names,
+        factor,
+        offset: 0.0,
+        dimension,
+    }
+}
+
+const UNITS: &[Unit] = &[
+    unit(&["m", "meter", "meters", "metre", "metres"], 1.0, LENGTH),
+    unit(&["km", "kilometer", "kilometers", "kilometre", "kilometres"], 1e3, LENGTH),
+    unit(&["cm", "centimeter", "centimeters", "centimetre", "centimetres"], 1e-2, LENGTH),
+    unit(&["mm", "millimeter", "millimeters", "millimetre", "millimetres"], 1e-3, LENGTH),
+    unit(&["um", "µm", "micrometer", "micrometers"], 1e-6, LENGTH),
+    unit(&["nm", "nanometer", "nanometers"], 1e-9, LENGTH),
+    unit(&["in", "inch", "inches"], 0.0254, LENGTH),
+    unit(&["ft", "foot", "feet"], 0.3048, LENGTH),
+    unit(&["yd", "yard", "yards"], 0.9144, LENGTH),
+    unit(&["mi", "mile", "miles"], 1609.344, LENGTH),
+    unit(&["nmi"], 1852.0, LENGTH),
+    unit(&["ha", "hectare", "hectares"], 1e4, AREA),
+    unit(&["acre", "acres"], 4046.8564224, AREA),
+    unit(&["l", "L", "liter", "liters", "litre", "litres"], 1e-3, VOLUME),
+    unit(&["ml", "mL", "milliliter", "milliliters", "millilitre", "millilitres"], 1e-6, VOLUME),
+    unit(&["gal", "gallon", "gallons"], 3.785411784e-3, VOLUME),
+    unit(&["qt", "quart", "quarts"], 9.46352946e-4, VOLUME),
+    unit(&["pt", "pint", "pints"], 4.73176473e-4, VOLUME),
+    unit(&["cup", "cups"], 2.365882365e-4, VOLUME),
+    unit(&["floz"], 2.95735295625e-5, VOLUME),
+    unit(&["kg", "kilogram", "kilograms"], 1.0, MASS),
+    unit(&["g", "gram", "grams"], 1e-3, MASS),
+    unit(&["mg", "milligram", "milligrams"], 1e-6, MASS),
+    unit(&["t", "tonne", "tonnes"], 1e3, MASS),
+    unit(&["lb", "lbs", "pound", "pounds"], 0.45359237, MASS),
+    unit(&["oz", "ounce", "ounces"], 0.028349523125, MASS),
+    unit(&["st", "stone", "stones"], 6.35029318, MASS),
+    unit(&["s", "sec", "second", "seconds"], 1.0, TIME),
+    unit(&["ms", "millisecond", "milliseconds"], 1e-3, TIME),
+    unit(&["min", "minute", "minutes"], 60.0, TIME),
+    unit(&["h", "hr", "hour", "hours"], 3600.0, TIME),
+    unit(&["d", "day", "days"], 86400.0, TIME),
+    unit(&["week", "weeks"], 604800.0, TIME),
+    unit(&["mph"], 0.44704, SPEED),
+    unit(&["kn", "knot", "knots"], 1852.0 / 3600.0, SPEED),
+    unit(&["K", "kelvin"], 1.0, TEMPERATURE),
+    Unit {
+        names: &["C", "°C", "celsius"],
+        factor: 1.0,
+        offset: 273.15,
+        dimension: TEMPERATURE,
+    },
+    Unit {
+        names: &["F", "°F", "fahrenheit"],
+        factor: 5.0 / 9.0,
+        offset: 459.67 * 5.0 / 9.0,
+        dimension: TEMPERATURE,
+    },
+    unit(&["B", "byte", "bytes"], 1.0, INFORMATION),
+    unit(&["bit", "bits"], 0.125, INFORMATION),
+    unit(&["KB", "kB"], 1e3, INFORMATION),
+    unit(&["MB"], 1e6, INFORMATION),
+    unit(&["GB"], 1e9, INFORMATION),
+    unit(&["TB"], 1e12, INFORMATION),
+    unit(&["KiB"], 1024.0, INFORMATION),
+    unit(&["MiB"], 1048576.0, INFORMATION),
+    unit(&["GiB"], 1073741824.0, INFORMATION),
+    unit(&["TiB"], 1099511627776.0, INFORMATION),
+];
+
+/// Find a unit by its name, ignoring the case of names longer than two chars.
+fn find_unit(name: &str) -> Option<&'static Unit> {
+    UNITS
+        .iter()
+        .find(|unit| unit.names.contains(&name))
+        .or_else(|| {
+            UNITS.iter().find(|unit| {
+                unit.names
+                    .iter()
+                    .any(|n| n.chars().count() > 2 && n.eq_ignore_ascii_case(name))
+            })
+        })
+}
+
+const CONVERSIONS: &[&str] = &["in", "to", "as"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token<'s> {
+    Number(f64),
+    Ident(&'s str),
+    Op(char),
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Token<'_>, Range<usize>)>, String> {
+    let is_ident = |c: char| c.is_alphabetic() || c == '°' || c == 'µ';
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            let mut seen_exponent = false;
+            while let Some(&(i, c)) = chars.peek() {
+                let is_exponent = !seen_exponent
+                    && (c == 'e' || c == 'E')
+                    && input[i + 1..]
+                        .trim_start_matches(['+', '-'])
+                        .starts_with(|c: char| c.is_ascii_digit());
+                if c.is_ascii_digit() || c == '.' {
+                    end = i + 1;
+                    chars.next();
+                } else if is_exponent {
+                    seen_exponent = true;
+                    chars.next();
+                    if let Some(&(i, '+' | '-')) = chars.peek() {
+                        end = i + 1;
+                        chars.next();
+                    }
+                } else {
+                    break;
+                }
+            }
+            let number = input[start..end]
+                .parse()
+                .map_err(|_| format!("`{}` is not a number", &input[start..end]))?;
+            tokens.push((Token::Number(number), start..end));
+        } else if is_ident(c) {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek().filter(|(_, c)| is_ident(*c)) {
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push((Token::Ident(&input[start..end]), start..end));
+        } else {
+            chars.next();
+            let range = start..start + c.len_utf8();
+            match c {
+                '+' | '-' | '*' | '/' | '%' | '^' | '(' | ')' | ',' => tokens.push((Token::Op(c), range)),
+                '×' | '·' => tokens.push((Token::Op('*'), range)),
+                '÷' => tokens.push((Token::Op('/'), range)),
+                '²' | '³' => {
+                    tokens.push((Token::Op('^'), range.clone()));
+                    tokens.push((Token::Number(if c == '²' { 2.0 } else { 3.0 }), range));
+                }
+                _ => return Err(format!("unexpected `{c}`")),
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Quantity {
+    /// The value in base units.
+    value: f64,
+    dimension: Dimension,
+    /// Whether it is a temperature in an offset unit like °C, which arithmetic cannot apply to.
+    absolute: bool,
+}
+
+impl Quantity {
+    fn number(value: f64) -> Self {
+        Self {
+            value,
+            dimension: DIMENSIONLESS,
+            absolute: false,
+        }
+    }
+
+    /// The quantity as an operand of arithmetic, which an absolute temperature cannot be.
+    fn operand(self) -> Result<Self, String> {
+        if self.absolute {
+            Err("temperatures in °C and °F cannot take part in arithmetic".to_string())
+        } else {
+            Ok(self)
+        }
+    }
+
+    fn multiply(self, other: Self, exponent: i8) -> Result<Self, String> {
+        let (this, other) = (self.operand()?, other.operand()?);
+        let mut dimension = this.dimension;
+        for (d, o) in dimension.iter_mut().zip(other.dimension) {
+            *d = o.checked_mul(exponent).and_then(|o| d.checked_add(o)).ok_or_else(too_large)?;
+        }
+        let value = if exponent < 0 {
+            this.value / other.value
+        } else {
+            this.value * other.value
+        };
+        Ok(Self {
+            value,
+            dimension,
+            absolute: false,
+        })
+    }
+
+    fn dimensionless(self, what: &str) -> Result<f64, String> {
+        if self.dimension == DIMENSIONLESS {
+            Ok(self.value)
+        } else {
+            Err(format!("{what} takes a number, got {}", format_units(self.dimension)))
+        }
+    }
+}
+
+fn too_large() -> String {
+    "units are too large".to_string()
+}
+
+fn format_units(dimension: Dimension) -> String {
+    let part = |sign: i8| -> Vec<String> {
+        BASE_UNITS
+            .iter()
+            .zip(dimension)
+            .filter(|(_, exponent)| exponent.signum() == sign)
+            .map(|(unit, exponent)| match exponent.abs() {
+                1 => unit.to_string(),
+                exponent => format!("{unit}^{exponent}"),
+            })
+            .collect()
+    };
+    let (numerator, denominator) = (part(1), part(-1));
+    match (numerator.is_empty(), denominator.is_empty()) {
+        (_, true) => numerator.join("·"),
+        (true, false) => format!("1/{}", denominator.join("/")),
+        (false, false) => format!("{}/{}", numerator.join("·"), denominator.join("/")),
+    }
+}
+
+/// Format a number with up to 12 significant digits.
+fn format_number(value: f64) -> String {
+    let magnitude = value.abs();
+    if magnitude == 0.0 {
+        return "0".to_string();
+    }
+    if !(1e-6..1e15).contains(&magnitude) {
+        return format!("{value:e}");
+    }
+    let digits = 12 - (magnitude.log10().floor() as i32 + 1);
+    let formatted = format!("{:.*}", digits.clamp(0, 15) as usize, value);
+    if formatted.contains('.') {
+        formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+    } else {
+        formatted
+    }
+}
+
+struct Parser<'t, 's> {
+    tokens: &'t [(Token<'s>, Range<usize>)],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'s> Parser<'_, 's> {
+    fn peek(&self) -> Option<Token<'s>> {
+        self.tokens.get(self.pos).map(|(token, _)| *token)
+    }
+
+    fn next(&mut self) -> Option<Token<'s>> {
+        let token = self.peek();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, op: char) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Op(c)) if c == op => Ok(()),
+            _ => Err(format!("expected `{op}`")),
+        }
+    }
+
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, String>) -> Result<T, String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err("the expression is nested too deeply".to_string());
+        }
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Parse all the tokens as one expression.
+    fn parse(tokens: &[(Token<'s>, Range<usize>)]) -> Result<Quantity, String> {
+        let mut parser = Parser { tokens, pos: 0, depth: 0 };
+        let quantity = parser.sum()?;
+        match parser.peek() {
+            None => Ok(quantity),
+            Some(_) => Err(format!("unexpected `{}`", token_text(&parser.tokens[parser.pos].0))),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Quantity, String> {
+        let mut left = self.product()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek() {
+            self.next();
+            left = left.operand()?;
+            let right = self.product()?.operand()?;
+            if left.dimension != right.dimension {
+                return Err(format!(
+                    "cannot {} {} and {}",
+                    if op == '+' { "add" } else { "subtract" },
+                    format_units(left.dimension),
+                    format_units(right.dimension)
+                ));
+            }
+            left.value += if op == '+' { right.value } else { -right.value };
+        }
+        Ok(left)
+    }
+
+    fn product(&mut self) -> Result<Quantity, String> {
+        let mut left = self.term()?;
+        loop {
+            left = match self.peek() {
+                Some(Token::Op('*')) => {
+                    self.next();
+                    left.multiply(self.term()?, 1)?
+                }
+                Some(Token::Op('/')) => {
+                    self.next();
+                    left.multiply(self.term()?, -1)?
+                }
+                Some(Token::Op('%')) => {
+                    self.next();
+                    left = left.operand()?;
+                    let right = self.term()?.operand()?;
+                    if left.dimension != right.dimension {
+                        return Err("`%` takes operands of the same units".to_string());
+                    }
+                    Quantity {
+                        value: left.value % right.value,
+                        ..left
+                    }
+                }
+                _ => return Ok(left),
+            };
+        }
+    }
+
+    /// Parse a juxtaposition, as in `3 ft` or `2 pi`, which binds tighter than `*` and `/`.
+    fn term(&mut self) -> Result<Quantity, String> {
+        let mut left = self.unary()?;
+        while let Some(Token::Number(_) | Token::Ident(_) | Token::Op('(')) = self.peek() {
+            left = left.multiply(self.unary()?, 1)?;
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Quantity, String> {
+        self.nested(|parser| match parser.peek() {
+            Some(Token::Op('-')) => {
+                parser.next();
+                // A negative temperature, not the negation of an absolute one.
+                if let Some(Token::Number(value)) = parser.peek() {
+                    parser.pos += 1;
+                    match parser.temperature(-value) {
+                        Some(quantity) => return Ok(quantity),
+                        None => parser.pos -= 1,
+                    }
+                }
+                let quantity = parser.unary()?.operand()?;
+                Ok(Quantity {
+                    value: -quantity.value,
+                    ..quantity
+                })
+            }
+            Some(Token::Op('+')) => {
+                parser.next();
+                parser.unary()
+            }
+            _ => parser.power(),
+        })
+    }
+
+    fn power(&mut self) -> Result<Quantity, String> {
+        let base = self.primary()?;
+        if self.peek() != Some(Token::Op('^')) {
+            return Ok(base);
+        }
+        let base = base.operand()?;
+        self.next();
+        let exponent = self.unary()?.dimensionless("`^`")?;
+        if base.dimension == DIMENSIONLESS {
+            return Ok(Quantity::number(base.value.powf(exponent)));
+        }
+        if exponent.fract() != 0.0 || exponent.abs() > 8.0 {
+            return Err("units can only be raised to small whole powers".to_string());
+        }
+        let mut dimension = base.dimension;
+        for d in dimension.iter_mut() {
+            *d = d.checked_mul(exponent as i8).ok_or_else(too_large)?;
+        }
+        Ok(Quantity {
+            value: base.value.powi(exponent as i32),
+            dimension,
+            absolute: false,
+        })
+    }
+
+    /// Take the temperature unit after a number, which cannot be scaled like other units.
+    fn temperature(&mut self, value: f64) -> Option<Quantity> {
+        match self.peek() {
+            Some(Token::Ident(name))
+                if let Some(unit) = find_unit(name)
+                    && unit.offset != 0.0 =>
+            {
+                self.next();
+                Some(Quantity {
+                    value: value * unit.factor + unit.offset,
+                    dimension: unit.dimension,
+                    absolute: true,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn primary(&mut self) -> Result<Quantity, String> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(self.temperature(value).unwrap_or(Quantity::number(value))),
+            Some(Token::Op('(')) => {
+                let quantity = self.nested(Self::sum)?;
+                self.expect(')')?;
+                Ok(quantity)
+            }
+            Some(Token::Ident(name)) if self.peek() == Some(Token::Op('(')) => {
+                self.next();
+                let mut args = vec![self.nested(Self::sum)?];
+                while self.peek() == Some(Token::Op(',')) {
+                    self.next();
+                    args.push(self.nested(Self::sum)?);
+                }
+                self.expect(')')?;
+                call(name, &args)
+            }
+            Some(Token::Ident("pi" | "π")) => Ok(Quantity::number(consts::PI)),
+            Some(Token::Ident("e")) => Ok(Quantity::number(consts::E)),
+            Some(Token::Ident(name)) => match find_unit(name) {
+                Some(unit) if unit.offset != 0.0 => Err(format!("`{name}` must follow a number")),
+                Some(unit) => Ok(Quantity {
+                    value: unit.factor,
+                    dimension: unit.dimension,
+                    absolute: false,
+                }),
+                None => Err(format!("unknown unit `{name}`")),
+            },
+            Some(token) => Err(format!("unexpected `{}`", token_text(&token))),
+            None => Err("unexpected end of the expression".to_string()),
+        }
+    }
+}
+
+fn token_text(token: &Token) -> String {
+    match token {
+        Token::Number(value) => format_number(*value),
+        Token::Ident(name) => name.to_string(),
+        Token::Op(op) => op.to_string(),
+    }
+}
+
+fn call(name: &str, args: &[Quantity]) -> Result<Quantity, String> {
+    let [arg] = args else {
+        return Err(format!("`{name}` takes one argument"));
+    };
+    let number = |f: fn(f64) -> f64| Ok(Quantity::number(f(arg.dimensionless(&format!("`{name}`"))?)));
+    match name {
+        "abs" => Ok(Quantity {
+            value: arg.value.abs(),
+            ..*arg
+        }),
+        "sqrt" => {
+            if arg.dimension.iter().any(|d| d % 2 != 0) {
+                return Err(format!("cannot take the square root of {}", format_units(arg.dimension)));
+            }
+            Ok(Quantity {
+                value: arg.value.sqrt(),
+                dimension: arg.dimension.map(|d| d / 2),
+                absolute: false,
+            })
+        }
+        "ln" => number(f64::ln),
+        "log" => number(f64::log10),
+        "exp" => number(f64::exp),
+        "sin" => number(f64::sin),
+        "cos" => number(f64::cos),
+        "tan" => number(f64::tan),
+        "asin" => number(f64::asin),
+        "acos" => number(f64::acos),
+        "atan" => number(f64::atan),
+        "round" => number(f64::round),
+        "floor" => number(f64::floor),
+        "ceil" => number(f64::ceil),
+        _ => Err(format!("unknown function `{name}`")),
+    }
+}
+
+/// Evaluate an expression, converting it to the units after its last `in`, `to` or `as` if any.
+fn evaluate(input: &str) -> Result<String, String> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Err("the expression is empty".to_string());
+    }
+    // A unit or a constant alone, like `week` or `e`, is more likely a word to search.
+    let has_number = tokens
+        .iter()
+        .any(|(token, range)| matches!(token, Token::Number(_)) && !matches!(&input[range.clone()], "²" | "³"));
+    if !has_number {
+        return Err("the expression has no number".to_string());
+    }
+
+    let conversion = tokens.iter().enumerate().rev().find_map(|(i, (token, range))| match token {
+        Token::Ident(keyword) if i > 0 && CONVERSIONS.contains(keyword) && i + 1 < tokens.len() => {
+            let target = &tokens[i + 1..];
+            let units = parse_units(target).ok()?;
+            let quantity = Parser::parse(&tokens[..i]).ok()?;
+            Some((quantity, units, target, input[range.end..].trim()))
+        }
+        _ => None,
+    });
+    let Some((quantity, units, target, target_text)) = conversion else {
+        let quantity = Parser::parse(&tokens)?;
+        return finish(quantity.value).map(|value| match quantity.dimension {
+            DIMENSIONLESS => value,
+            dimension => format!("{value} {}", format_units(dimension)),
+        });
+    };
+
+    if quantity.dimension != units.dimension {
+        return Err(format!(
+            "cannot convert {} to {target_text}",
+            format_units(quantity.dimension)
+        ));
+    }
+    let value = match target {
+        [(Token::Ident(name), _)] if let Some(unit) = find_unit(name) => (quantity.value - unit.offset) / unit.factor,
+        _ => quantity.value / units.value,
+    };
+    finish(value).map(|value| format!("{value} {target_text}"))
+}
+
+/// Parse the units to convert to, which may be a temperature alone.
+fn parse_units(tokens: &[(Token<'_>, Range<usize>)]) -> Result<Quantity, String> {
+    match tokens {
+        [(Token::Ident(name), _)] if let Some(unit) = find_unit(name) => Ok(Quantity {
+            value: unit.factor,
+            dimension: unit.dimension,
+            absolute: false,
+        }),
+        tokens => Parser::parse(tokens),
+    }
+}
+
+fn finish(value: f64) -> Result<String, String> {
+    if value.is_finite() {
+        Ok(format_number(value))
+    } else {
+        Err("the result is not a finite number".to_string())
+    }
+}
+
+pub struct Calc {
+    identifier: String,
+}
+
+impl Engine for Calc {
+    fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    fn react<'e, 'q: 'e, 'i: 'e>(
+        &'e self,
+        query: &'q Query,
+        _instance: &'i Instance,
+    ) -> impl Future<Output = Reaction> + Send + 'e {
+        let reaction = evaluate(query.content())
+            .map(|answer| Answer::new(answer).into())
+            .map_err(|reason| {
+                AcceptanceErr::Rejected {
+                    id: self.identifier.clone(),
+                    reason,
+                }
+                .into()
+            });
+
+        async move { reaction }
+    }
+}
+
+impl From<Calc> for EngineNode {
+    fn from(calc: Calc) -> Self {
+        Self::Calc(calc)
+    }
+}
+
+pub(crate) mod compose {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug)]
+    pub(crate) struct Calc {}
+
+    impl Calc {
+        pub(crate) fn build(self, identifier: String) -> crate::engine::EngineNode {
+            super::Calc { identifier }.into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{evaluate, format_number};
+
+    #[test]
+    fn test_arithmetic() {
+        for (input, answer) in [
+            ("1 + 2 * 3", "7"),
+            ("(1 + 2) * 3", "9"),
+            ("2 ^ 3 ^ 2", "512"),
+            ("-2^2", "-4"),
+            ("7 % 3", "1"),
+            ("1/3", "0.333333333333"),
+            ("2 pi", "6.28318530718"),
+            ("sqrt(16) + abs(-1)", "5"),
+            ("1.5e3 × 2", "3000"),
+            ("0.1 + 0.2", "0.3"),
+        ] {
+            assert_eq!(evaluate(input).as_deref(), Ok(answer), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_units() {
+        for (input, answer) in [
+            ("3 ft in cm", "91.44 cm"),
+            ("3 in in cm", "7.62 cm"),
+            ("1 mi to km", "1.609344 km"),
+            ("60 mph as km/h", "96.56064 km/h"),
+            ("100 C in F", "212 F"),
+            ("-40 °F to °C", "-40 °C"),
+            ("2 GiB in MB", "2147.483648 MB"),
+            ("3 m²", "3 m^2"),
+            ("1 km + 300 m", "1300 m"),
+            ("10 m / 2 s", "5 m/s"),
+            ("10 K + 5 K", "15 K"),
+            ("(-10 °C) in K", "263.15 K"),
+            ("1 acre in m^2", "4046.8564224 m^2"),
+        ] {
+            assert_eq!(evaluate(input).as_deref(), Ok(answer), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        for input in ["", "rust programming", "3 ft in kg", "1 m + 1 s", "1 / 0", "C", "(((1)"] {
+            assert!(evaluate(input).is_err(), "{input}");
+        }
+        assert!(evaluate(&"(".repeat(1000)).is_err());
+        assert_eq!(evaluate("1 ((m^8)^8)^8"), Err("units are too large".to_string()));
+        assert_eq!(evaluate(&format!("1 m{}", "*m".repeat(200))), Err("units are too large".to_string()));
+        for input in ["10 C + 5 C", "10 °C - 5 K", "2 * 10 C", "10 C / 2", "-(10 °F)", "(10 C)^2", "10 C % 3 C"] {
+            let error = "temperatures in °C and °F cannot take part in arithmetic";
+            assert_eq!(evaluate(input), Err(error.to_string()), "{input}");
+        }
+        for input in ["stone", "week", "e", "m", "g", "day", "pi", "km/h", "m²", "ft in cm"] {
+            assert_eq!(evaluate(input), Err("the expression has no number".to_string()), "{input}");
+        }
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(1e20), "1e20");
+        assert_eq!(format_number(-0.0), "0");
+        assert_eq!(format_number(123.456), "123.456");
+    }
+}
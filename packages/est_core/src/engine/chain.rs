@@ -0,0 +1,63 @@
+//! An engine that tries engines in order, falling back to the next one while they reject the query.
+//!
+//! The first reaction that is not a recoverable error is taken,
+//!   so an engine that rejects the query or finds nothing passes it on,
+//!   while an engine that fails stops the chain with its error.
+//! If every engine passes, the chain fails with the error of the last one.
+use super::{Engine, EngineNode};
+use crate::{Instance, Query, Reaction, reaction::Forward};
+use std::future::Future;
+
+pub struct Chain {
+    identifier: String,
+    targets: Vec<String>,
+}
+
+impl Engine for Chain {
+    fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    fn react<'e, 'q: 'e, 'i: 'e>(
+        &'e self,
+        _query: &'q Query,
+        _instance: &'i Instance,
+    ) -> impl Future<Output = Reaction> + Send + 'e {
+        let reaction = Forward::Fallback(self.targets.clone(), 1);
+
+        async move { Ok(reaction.into()) }
+    }
+}
+
+impl From<Chain> for EngineNode {
+    fn from(chain: Chain) -> Self {
+        Self::Chain(chain)
+    }
+}
+
+pub(crate) mod compose {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug)]
+    pub(crate) struct Chain {
+        /// Engines to try, in order.
+        pub targets: Vec<String>,
+    }
+
+    impl Chain {
+        pub(crate) fn build(self, identifier: String) -> crate::engine::EngineNode {
+            if self.targets.is_empty() {
+                panic!("Chain engine {} has no targets", identifier);
+            }
+            if self.targets.contains(&identifier) {
+                panic!("Chain engine {} has itself as a target", identifier);
+            }
+
+            super::Chain {
+                identifier,
+                targets: self.targets,
+            }
+            .into()
+        }
+    }
+}
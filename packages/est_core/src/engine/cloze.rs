@@ -1,23 +1,30 @@
 //! A simple engine that fills the designated blank with the query content.
 //! This is similar to Chrome and Firefox's custom search engine feature,
 //!   excepts that we use `{}` as a placeholder for the query.
+//!
+//! Templates are compiled when the engine is built, see [`crate::template`] for the syntax.
+//! The engine may declare parameters for the rest of its mention, which templates read as `{arg.name}`,
+//!   see [`crate::arguments`].
 use super::{Engine, EngineNode};
+use crate::arguments::Params;
 use crate::reaction::Navigate;
-use crate::{Instance, Query, Reaction};
+use crate::template::Template;
+use crate::{AcceptanceErr, Instance, Query, Reaction};
 use std::future::Future;
 
 pub struct Cloze {
     identifier: String,
-    template: String,
+    params: Params,
+    template: Template,
 }
 
 pub struct ClozeScoped {
     identifier: String,
-    template_default: String,
-    template_scoped: String,
+    params: Params,
+    template_default: Template,
+    template_scoped: Template,
 }
 
-
 impl Engine for Cloze {
     fn identifier(&self) -> &str {
         &self.identifier
@@ -28,9 +35,16 @@ impl Engine for Cloze {
         query: &'q Query,
         _instance: &'i Instance,
     ) -> impl Future<Output = Reaction> + Send + 'e {
-        let url = self.template.replace("{}", query.content());
+        let url = self
+            .params
+            .bind(&self.identifier, query.mention_tail())
+            .map(|arguments| self.template.render(query, &arguments));
+
+        async { Navigate::from_str(url?, true) }
+    }
 
-        async { Navigate::from_str(url, true) }
+    fn accept(&self, query: &Query, _instance: &Instance) -> Result<(), AcceptanceErr> {
+        self.params.bind(&self.identifier, query.mention_tail()).map(drop)
     }
 }
 
@@ -44,13 +58,25 @@ impl Engine for ClozeScoped {
         query: &'q Query,
         _instance: &'i Instance,
     ) -> impl Future<Output = Reaction> + Send + 'e {
-        let url = if let Some(scope) = query.scope.as_ref() {
-            self.template_scoped.replace("{!}", scope).replace("{}", query.content())
+        let is_scoped = self
+            .template_scoped
+            .scope_keys()
+            .any(|key| query.scope(key).is_some());
+        let template = if is_scoped {
+            &self.template_scoped
         } else {
-            self.template_default.replace("{}", query.content())
+            &self.template_default
         };
+        let url = self
+            .params
+            .bind(&self.identifier, query.mention_tail())
+            .map(|arguments| template.render(query, &arguments));
+
+        async { Navigate::from_str(url?, true) }
+    }
 
-        async { Navigate::from_str(url, true) }
+    fn accept(&self, query: &Query, _instance: &Instance) -> Result<(), AcceptanceErr> {
+        self.params.bind(&self.identifier, query.mention_tail()).map(drop)
     }
 }
 
@@ -67,11 +93,15 @@ impl From<ClozeScoped> for EngineNode {
 }
 
 pub(crate) mod compose {
+    use crate::arguments::{Param, Params};
+    use crate::template::Template;
     use serde::{Deserialize, Serialize};
 
     #[derive(Deserialize, Serialize, Debug)]
     pub(crate) struct Cloze {
         pub template: ClozeTemplate,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub args: Vec<Param>,
     }
 
     #[derive(Deserialize, Serialize, Debug)]
@@ -84,19 +114,34 @@ pub(crate) mod compose {
         },
     }
 
+    fn compile(template: &str, params: &Params) -> Template {
+        let compiled: Template = template
+            .parse()
+            .unwrap_or_else(|err| panic!("Invalid template in cloze engine `{}`: {}", template, err));
+        if let Some(name) = compiled.arg_names().find(|name| !params.contains(name)) {
+            panic!("Template `{}` reads the undeclared argument `{}`", template, name);
+        }
+        compiled
+    }
+
     impl Cloze {
         pub(crate) fn build(self, identifier: String) -> crate::engine::EngineNode {
+            let params = Params::try_from(self.args)
+                .unwrap_or_else(|err| panic!("Invalid arguments of cloze engine `{}`: {}", identifier, err));
             match self.template {
                 ClozeTemplate::Single(template) => super::Cloze {
                     identifier,
-                    template,
+                    template: compile(&template, &params),
+                    params,
                 }.into(),
                 ClozeTemplate::Scoped { default, scoped } => super::ClozeScoped {
                     identifier,
-                    template_default: default,
-                    template_scoped: scoped,
+                    template_default: compile(&default, &params),
+                    template_scoped: compile(&scoped, &params),
+                    params,
                 }.into(),
             }
         }
     }
 }
+
@@ -0,0 +1,232 @@
+//! An engine that navigates straight to the address when the query content is already one,
+//!   like `https://example.com`, `docs.rs/serde` or `localhost:8080`.
+//! Otherwise it forwards to the default engine.
+//!
+//! Since an engine only sees the content, `@g example.com` still searches with `g`.
+//! Only `http` and `https` URLs are navigated to, unless other schemes are configured,
+//!   so that `javascript:` or `file:` addresses are searched for instead.
+use super::{Engine, EngineNode};
+use crate::{
+    reaction::{Forward, Navigate},
+    Instance, Query, Reaction,
+};
+use serde::{Deserialize, Serialize};
+use std::{future::Future, net::Ipv4Addr};
+use url::Url;
+
+/// Top-level domains that make a bare host like `example.com` count as an address.
+const KNOWN_TLDS: &[&str] = &[
+    "ai", "app", "at", "au", "be", "blog", "br", "ca", "cc", "ch", "cloud", "cn", "co", "com",
+    "cz", "de", "dev", "dk", "edu", "es", "eu", "fi", "fm", "fr", "gg", "gov", "hk", "ie", "in",
+    "info", "int", "io", "it", "jp", "kr", "li", "ly", "me", "mil", "net", "nl", "no", "nz",
+    "org", "page", "pl", "pt", "ru", "rs", "se", "sh", "site", "so", "tech", "to", "tv", "tw",
+    "uk", "us", "wiki", "xyz",
+];
+
+/// Known top-level domains that are also common file extensions, like `main.rs` or `install.sh`.
+/// A bare host with one of them only counts with a path, a port or `www.`, like `docs.rs/serde`.
+const FILE_LIKE_TLDS: &[&str] = &["cc", "in", "pl", "rs", "sh", "so"];
+
+/// How eagerly the content is taken as an address.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Eagerness {
+    /// Only URLs with an explicit scheme, like `https://example.com`.
+    Scheme,
+    /// Also bare domains with a known top-level domain, and `localhost` with a port.
+    /// Domains whose top-level domain is also a file extension, like `main.rs`, need a path, a port or `www.`.
+    #[default]
+    KnownTld,
+    /// Also any dotted host name, IPv4 addresses, and `localhost` alone.
+    AnyHost,
+}
+
+pub struct Direct {
+    identifier: String,
+    default: String,
+    eagerness: Eagerness,
+    schemes: Vec<String>,
+}
+
+/// Schemes that are always navigated to.
+const WEB_SCHEMES: &[&str] = &["http", "https"];
+
+fn is_label(label: &str) -> bool {
+    !label.is_empty()
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+        && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+}
+
+/// The scheme to navigate to a bare host with, if it looks like an address,
+///   given whether the content has a path after the host.
+fn host_scheme(host: &str, has_path: bool, eagerness: Eagerness) -> Option<&'static str> {
+    let (name, port) = match host.rsplit_once(':') {
+        Some((name, port)) => (name, Some(port)),
+        None => (host, None),
+    };
+    if port.is_some_and(|port| port.is_empty() || !port.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+
+    if name.eq_ignore_ascii_case("localhost") {
+        let is_address = match eagerness {
+            Eagerness::Scheme => false,
+            Eagerness::KnownTld => port.is_some(),
+            Eagerness::AnyHost => true,
+        };
+        return is_address.then_some("http");
+    }
+    if name.parse::<Ipv4Addr>().is_ok() {
+        return (eagerness >= Eagerness::AnyHost).then_some("http");
+    }
+
+    let labels: Vec<&str> = name.split('.').collect();
+    if labels.len() < 2 || !labels.iter().all(|label| is_label(label)) {
+        return None;
+    }
+    let tld = labels[labels.len() - 1];
+    let is_address = match eagerness {
+        Eagerness::Scheme => false,
+        Eagerness::KnownTld => {
+            let is_known = |tlds: &[&str]| tlds.iter().any(|known| known.eq_ignore_ascii_case(tld));
+            is_known(KNOWN_TLDS)
+                && (!is_known(FILE_LIKE_TLDS)
+                    || has_path
+                    || port.is_some()
+                    || labels[0].eq_ignore_ascii_case("www"))
+        }
+        Eagerness::AnyHost => tld.len() >= 2 && tld.chars().all(char::is_alphabetic),
+    };
+    is_address.then_some("https")
+}
+
+/// Find the address the content refers to, if any,
+///   taking URLs with an explicit scheme only for the web schemes and the extra `schemes`.
+fn detect(content: &str, eagerness: Eagerness, schemes: &[String]) -> Option<Url> {
+    if content.is_empty() || content.chars().any(char::is_whitespace) {
+        return None;
+    }
+
+    if content.contains("://") {
+        let url = Url::parse(content).ok()?;
+        let is_allowed = WEB_SCHEMES.contains(&url.scheme())
+            || schemes.iter().any(|scheme| scheme.eq_ignore_ascii_case(url.scheme()));
+        return is_allowed.then_some(url);
+    }
+
+    let host = content
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or(content);
+    let has_path = content[host.len()..].starts_with('/');
+    let scheme = host_scheme(host, has_path, eagerness)?;
+    Url::parse(&format!("{scheme}://{content}")).ok()
+}
+
+impl Engine for Direct {
+    fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    fn react<'e, 'q: 'e, 'i: 'e>(
+        &'e self,
+        query: &'q Query,
+        _instance: &'i Instance,
+    ) -> impl Future<Output = Reaction> + Send + 'e {
+        let reaction = match detect(query.content(), self.eagerness, &self.schemes) {
+            Some(url) => Navigate::from_str(url, false),
+            None => Ok(Forward::Mention(self.default.clone(), 1).into()),
+        };
+
+        async move { reaction }
+    }
+}
+
+impl From<Direct> for EngineNode {
+    fn from(direct: Direct) -> Self {
+        Self::Direct(direct)
+    }
+}
+
+pub(crate) mod compose {
+    use super::Eagerness;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug)]
+    pub(crate) struct Direct {
+        pub default: String,
+        #[serde(default)]
+        pub eagerness: Eagerness,
+        /// Schemes navigated to besides `http` and `https`, like `ftp`.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub schemes: Vec<String>,
+    }
+
+    impl Direct {
+        pub(crate) fn build(self, identifier: String) -> crate::engine::EngineNode {
+            super::Direct {
+                identifier,
+                default: self.default,
+                eagerness: self.eagerness,
+                schemes: self.schemes,
+            }
+            .into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{detect, Eagerness};
+
+    #[test]
+    fn test_detect() {
+        use Eagerness::*;
+        for (content, eagerness, expected) in [
+            ("https://example.com", Scheme, Some("https://example.com/")),
+            ("example.com", Scheme, None),
+            ("docs.rs/serde", KnownTld, Some("https://docs.rs/serde")),
+            ("Example.COM?q=1", KnownTld, Some("https://example.com/?q=1")),
+            ("localhost:8080/api", KnownTld, Some("http://localhost:8080/api")),
+            ("localhost", KnownTld, None),
+            ("localhost:8080", Scheme, None),
+            ("main.rs", KnownTld, None),
+            ("lib.rs", KnownTld, None),
+            ("install.sh", KnownTld, None),
+            ("a.so", KnownTld, None),
+            ("Makefile.in", KnownTld, None),
+            ("docs.rs", KnownTld, None),
+            ("docs.rs:443", KnownTld, Some("https://docs.rs/")),
+            ("www.example.rs", KnownTld, Some("https://www.example.rs/")),
+            ("main.py", KnownTld, None),
+            ("e.g.", KnownTld, None),
+            ("rust async", KnownTld, None),
+            ("user@example.com", KnownTld, None),
+            ("example.local:3000", KnownTld, None),
+            ("example.local:3000", AnyHost, Some("https://example.local:3000/")),
+            ("192.168.1.1", KnownTld, None),
+            ("192.168.1.1:80", AnyHost, Some("http://192.168.1.1/")),
+            ("localhost", AnyHost, Some("http://localhost/")),
+            ("3.14", AnyHost, None),
+            ("javascript://example.com/%0aalert(1)", KnownTld, None),
+            ("file:///etc/passwd", AnyHost, None),
+            ("ftp://example.com/pub", KnownTld, None),
+        ] {
+            assert_eq!(
+                detect(content, eagerness, &[]).as_ref().map(|url| url.as_str()),
+                expected,
+                "content: {content}, eagerness: {eagerness:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_detect_schemes() {
+        let schemes = ["ftp".to_string()];
+        let detect = |content| detect(content, Eagerness::KnownTld, &schemes).map(String::from);
+        assert_eq!(detect("FTP://example.com/pub").as_deref(), Some("ftp://example.com/pub"));
+        assert_eq!(detect("https://example.com").as_deref(), Some("https://example.com/"));
+        assert_eq!(detect("file:///etc/passwd"), None);
+    }
+}
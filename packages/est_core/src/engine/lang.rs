@@ -0,0 +1,164 @@
+//! An engine that forwards to specified engines based on the language of the query content.
+//!
+//! The language is identified offline by the n-grams of the content.
+//! Unless the identification is confident enough, or the language has no engine, the default engine is used.
+//! Restricting the identification to the languages with engines makes it more accurate among them,
+//!   e.g. text of kanji only counts as Japanese unless Chinese has an engine as well.
+use super::{Engine, EngineNode};
+use crate::{Instance, Query, Reaction, reaction::Forward};
+use std::{collections::HashMap, future::Future};
+use whatlang::{Detector, Lang as Language};
+
+pub struct Lang {
+    identifier: String,
+    default: String,
+    /// The minimum confidence of an identification, or else whether it is reliable.
+    threshold: Option<f64>,
+    languages: HashMap<Language, String>,
+    detector: Detector,
+}
+
+impl Lang {
+    /// The engine the query should be forwarded to.
+    fn route(&self, content: &str) -> &str {
+        self.detector
+            .detect(content)
+            .filter(|info| match self.threshold {
+                Some(threshold) => info.confidence() >= threshold,
+                None => info.is_reliable(),
+            })
+            .and_then(|info| self.languages.get(&info.lang()))
+            .unwrap_or(&self.default)
+    }
+}
+
+impl Engine for Lang {
+    fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    fn react<'e, 'q: 'e, 'i: 'e>(
+        &'e self,
+        query: &'q Query,
+        _instance: &'i Instance,
+    ) -> impl Future<Output = Reaction> + Send + 'e {
+        let reaction = Forward::Mention(self.route(query.content()).to_owned(), 1);
+
+        async move { Ok(reaction.into()) }
+    }
+}
+
+impl From<Lang> for EngineNode {
+    fn from(lang: Lang) -> Self {
+        Self::Lang(lang)
+    }
+}
+
+pub(crate) mod compose {
+    use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeMap, HashMap};
+    use whatlang::{Detector, Lang as Language};
+
+    #[derive(Deserialize, Serialize, Debug)]
+    pub(crate) struct Lang {
+        pub default: String,
+        /// Engines by language, as an ISO 639-3 code like `deu`, or a name like `German`.
+        pub languages: BTreeMap<String, String>,
+        #[serde(default)]
+        pub threshold: Option<f64>,
+        /// Identify only the languages with engines.
+        #[serde(default)]
+        pub restrict: bool,
+    }
+
+    fn get_language(name: &str) -> Language {
+        Language::from_code(name.to_lowercase())
+            .or_else(|| {
+                Language::all().iter().copied().find(|language| {
+                    language.eng_name().eq_ignore_ascii_case(name) || language.name() == name
+                })
+            })
+            .unwrap_or_else(|| panic!("Unknown language in lang engine: {}", name))
+    }
+
+    impl Lang {
+        pub(crate) fn build(self, identifier: String) -> crate::engine::EngineNode {
+            if let Some(threshold) = self.threshold
+                && !(0.0..=1.0).contains(&threshold)
+            {
+                panic!("Threshold of lang engine {} is not within 0 and 1: {}", identifier, threshold);
+            }
+
+            let languages: HashMap<_, _> = self
+                .languages
+                .into_iter()
+                .map(|(name, to)| (get_language(&name), to))
+                .collect();
+            let detector = if self.restrict {
+                Detector::with_allowlist(languages.keys().copied().collect())
+            } else {
+                Detector::new()
+            };
+
+            super::Lang {
+                identifier,
+                default: self.default,
+                threshold: self.threshold,
+                languages,
+                detector,
+            }
+            .into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::compose;
+    use crate::engine::EngineNode;
+
+    fn lang(config: &str) -> super::Lang {
+        let lang: compose::Lang = toml::from_str(config).unwrap();
+        let EngineNode::Lang(lang) = lang.build("lang".to_string()) else {
+            panic!("Expected a lang engine");
+        };
+        lang
+    }
+
+    #[test]
+    fn test_route() {
+        let wiki = lang(
+            r#"
+            default = "wikipedia_en"
+            threshold = 0.5
+            languages = { deu = "wikipedia_de", French = "wikipedia_fr", cmn = "wikipedia_zh", jpn = "wikipedia_ja" }
+            "#,
+        );
+        assert_eq!(wiki.route("Die Geschichte der deutschen Sprache"), "wikipedia_de");
+        assert_eq!(wiki.route("Comment la langue française a-t-elle évolué au cours des siècles"), "wikipedia_fr");
+        assert_eq!(wiki.route("The history of the English language"), "wikipedia_en");
+        assert_eq!(wiki.route("日本語の歴史"), "wikipedia_ja");
+        assert_eq!(wiki.route("汉语的历史"), "wikipedia_zh");
+        assert_eq!(wiki.route("42"), "wikipedia_en");
+
+        let restricted = lang(
+            r#"
+            default = "wikipedia_en"
+            restrict = true
+            languages = { jpn = "wikipedia_ja" }
+            "#,
+        );
+        assert_eq!(restricted.route("東京大学"), "wikipedia_ja");
+    }
+
+    #[test]
+    #[should_panic(expected = "Unknown language in lang engine")]
+    fn test_unknown_language() {
+        lang(
+            r#"
+            default = "wikipedia_en"
+            languages = { klingon = "wikipedia_tlh" }
+            "#,
+        );
+    }
+}
@@ -48,7 +48,10 @@ impl Engine for Namespace {
 
     fn accept(&self, _query: &Query, _instance: &Instance) -> Result<(), AcceptanceErr> {
         if self.default.is_none() {
-            Err(AcceptanceErr::NoEngine)
+            Err(AcceptanceErr::Rejected {
+                id: self.identifier.clone(),
+                reason: "no default engine".to_string(),
+            })
         } else {
             Ok(())
         }
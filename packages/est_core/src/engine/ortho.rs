@@ -1,32 +1,74 @@
 //! An engine that forwards to specified engines based on Unicode script type.
+//!
+//! A rule matches a set of scripts, such as Han, Hiragana and Katakana for Japanese.
+//! By default, any char in the set is enough,
+//!   and the rule can further require a minimum count, a minimum ratio of the chars,
+//!   or that the set is the dominant script of the content.
+//! Only chars of a specific script are counted, leaving out spaces, digits and punctuation.
 use super::{Engine, EngineNode};
 use crate::{reaction::Forward, Instance, Query, Reaction};
 use icu_properties::{script, Script};
-use std::{future::Future, sync::LazyLock};
-
-static UNICODE_SCRIPT: LazyLock<script::ScriptWithExtensionsBorrowed<'_>> =
-    LazyLock::new(|| script::script_with_extensions());
-
-pub enum Ortho {
-    Single {
-        identifier: String,
-        script: Script,
-        to: String,
-        default: String,
-    },
-    Hierarchical {
-        identifier: String,
-        default: String,
-        scripts: Vec<(Script, String)>,
-    },
+use std::{collections::HashMap, future::Future, sync::LazyLock};
+
+pub(super) static UNICODE_SCRIPT: LazyLock<script::ScriptWithExtensionsBorrowed<'_>> =
+    LazyLock::new(script::script_with_extensions);
+
+/// A set of scripts, and how much of the content has to be in them.
+pub struct ScriptSet {
+    scripts: Vec<Script>,
+    min_count: usize,
+    min_ratio: f64,
+    dominant: bool,
+}
+
+impl ScriptSet {
+    pub(super) fn matches(&self, content: &str) -> bool {
+        let (mut count, mut total) = (0, 0);
+        let mut others: HashMap<Script, usize> = HashMap::new();
+        for c in content.chars() {
+            let c = c as u32;
+            if self.scripts.iter().any(|script| UNICODE_SCRIPT.has_script(c, *script)) {
+                count += 1;
+            } else {
+                match UNICODE_SCRIPT.get_script_val(c) {
+                    Script::Common | Script::Inherited | Script::Unknown => continue,
+                    script => *others.entry(script).or_default() += 1,
+                }
+            }
+            total += 1;
+        }
+
+        count >= self.min_count.max(1)
+            && count as f64 >= self.min_ratio * total as f64
+            && (!self.dominant || others.values().all(|other| *other < count))
+    }
+}
+
+pub struct OrthoRule {
+    scripts: ScriptSet,
+    to: String,
+}
+
+pub struct Ortho {
+    identifier: String,
+    default: String,
+    rules: Vec<OrthoRule>,
+}
+
+impl Ortho {
+    /// The engine the query should be forwarded to.
+    fn route(&self, content: &str) -> &str {
+        self.rules
+            .iter()
+            .find(|rule| rule.scripts.matches(content))
+            .map(|rule| rule.to.as_str())
+            .unwrap_or(&self.default)
+    }
 }
 
 impl Engine for Ortho {
     fn identifier(&self) -> &str {
-        match self {
-            Self::Single { identifier, .. } => identifier,
-            Self::Hierarchical { identifier, .. } => identifier,
-        }
+        &self.identifier
     }
 
     fn react<'e, 'q: 'e, 'i: 'e>(
@@ -34,38 +76,7 @@ impl Engine for Ortho {
         query: &'q Query,
         _instance: &'i Instance,
     ) -> impl Future<Output = Reaction> + Send + 'e {
-        let engine_id = match self {
-            Self::Single {
-                script,
-                to,
-                default,
-                ..
-            } => {
-                let has_script = query
-                    .content
-                    .chars()
-                    .any(|c| UNICODE_SCRIPT.has_script(c as u32, *script));
-                if has_script {
-                    to
-                } else {
-                    default
-                }
-            }
-            Self::Hierarchical {
-                default, scripts, ..
-            } => scripts
-                .iter()
-                .find(|(script, _)| {
-                    query
-                        .content
-                        .chars()
-                        .any(|c| UNICODE_SCRIPT.has_script(c as u32, *script))
-                })
-                .map(|(_, to)| to)
-                .unwrap_or(default),
-        };
-
-        let reaction = Forward::Mention(engine_id.to_owned(), 1);
+        let reaction = Forward::Mention(self.route(&query.content).to_owned(), 1);
 
         async move { Ok(reaction.into()) }
     }
@@ -81,10 +92,33 @@ pub(crate) mod compose {
     use icu_properties::Script;
     use serde::{Deserialize, Serialize};
 
+    /// A script, or a set of scripts counted together.
+    #[derive(Deserialize, Serialize, Debug)]
+    #[serde(untagged)]
+    pub(crate) enum ScriptNames {
+        Single(String),
+        Multiple(Vec<String>),
+    }
+
+    /// How much of the content has to be in the scripts, any char by default.
+    #[derive(Deserialize, Serialize, Debug, Default)]
+    pub(crate) struct Threshold {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub min_count: Option<usize>,
+        /// The minimum ratio of the chars of specific scripts.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub min_ratio: Option<f64>,
+        /// Whether the scripts have to outnumber every other script.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        pub dominant: bool,
+    }
+
     #[derive(Deserialize, Serialize, Debug)]
     pub(crate) struct OrthoScript {
-        pub script: String,
+        pub script: ScriptNames,
         pub to: String,
+        #[serde(flatten)]
+        pub threshold: Threshold,
     }
 
     #[derive(Debug, Serialize, Deserialize)]
@@ -101,38 +135,181 @@ pub(crate) mod compose {
         },
     }
 
-    fn get_script(script: OrthoScript) -> (Script, String) {
-        let OrthoScript { script, to } = script;
-        let script = Script::name_to_enum_mapper()
-            .get_loose(script.as_str())
-            .unwrap_or_else(|| panic!("Invalid script name in ortho engine: {}", script));
-        (script, to)
+    /// Build a set of scripts, panicking with the kind of engine on invalid names or thresholds.
+    pub(in crate::engine) fn get_script_set(
+        names: ScriptNames,
+        threshold: Threshold,
+        engine: &str,
+    ) -> super::ScriptSet {
+        let names = match names {
+            ScriptNames::Single(name) => vec![name],
+            ScriptNames::Multiple(names) => names,
+        };
+        if names.is_empty() {
+            panic!("Empty script set in {} engine", engine);
+        }
+        let scripts = names
+            .iter()
+            .map(|name| {
+                Script::name_to_enum_mapper()
+                    .get_loose(name)
+                    .unwrap_or_else(|| panic!("Invalid script name in {} engine: {}", engine, name))
+            })
+            .collect();
+
+        let Threshold {
+            min_count,
+            min_ratio,
+            dominant,
+        } = threshold;
+        if let Some(ratio) = min_ratio
+            && !(0.0..=1.0).contains(&ratio)
+        {
+            panic!("Ratio of scripts in {} engine is not within 0 and 1: {}", engine, ratio);
+        }
+
+        super::ScriptSet {
+            scripts,
+            min_count: min_count.unwrap_or(1),
+            min_ratio: min_ratio.unwrap_or(0.0),
+            dominant,
+        }
+    }
+
+    fn get_rule(script: OrthoScript) -> super::OrthoRule {
+        let OrthoScript {
+            script,
+            to,
+            threshold,
+        } = script;
+        super::OrthoRule {
+            scripts: get_script_set(script, threshold, "ortho"),
+            to,
+        }
     }
 
     impl Ortho {
         pub(crate) fn build(self, identifier: String) -> crate::engine::EngineNode {
-            match self {
-                Self::Single { default, script } => {
-                    let (script, to) = get_script(script);
-
-                    super::Ortho::Single {
-                        identifier,
-                        script,
-                        to,
-                        default,
-                    }
-                }
+            let (default, rules) = match self {
+                Self::Single { default, script } => (default, vec![get_rule(script)]),
                 Self::Hierarchical { default, scripts } => {
-                    let scripts = scripts.into_iter().map(get_script).collect();
-
-                    super::Ortho::Hierarchical {
-                        identifier,
-                        default,
-                        scripts,
-                    }
+                    (default, scripts.into_iter().map(get_rule).collect())
                 }
+            };
+
+            super::Ortho {
+                identifier,
+                default,
+                rules,
             }
             .into()
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::compose::{get_script_set, Ortho, ScriptNames, Threshold};
+    use crate::engine::EngineNode;
+
+    fn set(names: &[&str], threshold: Threshold) -> super::ScriptSet {
+        let names = ScriptNames::Multiple(names.iter().map(|name| name.to_string()).collect());
+        get_script_set(names, threshold, "ortho")
+    }
+
+    #[test]
+    fn test_any() {
+        let han = set(&["han"], Threshold::default());
+        assert!(han.matches("你好"));
+        assert!(han.matches("Vec<T> 用法"));
+        assert!(!han.matches("Vec<T> usage"));
+        assert!(!han.matches("123"));
+    }
+
+    #[test]
+    fn test_threshold() {
+        let ratio = set(
+            &["han"],
+            Threshold {
+                min_ratio: Some(0.5),
+                ..Default::default()
+            },
+        );
+        assert!(ratio.matches("发布会 2024"));
+        assert!(ratio.matches("Rust 用法说明"));
+        assert!(!ratio.matches("Vec<T> 用法"));
+        assert!(!ratio.matches("iPhone 发布会"));
+
+        let count = set(
+            &["han"],
+            Threshold {
+                min_count: Some(3),
+                ..Default::default()
+            },
+        );
+        assert!(count.matches("iPhone 发布会"));
+        assert!(!count.matches("Vec<T> 用法"));
+
+        let dominant = set(
+            &["han"],
+            Threshold {
+                dominant: true,
+                ..Default::default()
+            },
+        );
+        assert!(dominant.matches("用法说明 Vec"));
+        assert!(!dominant.matches("用法 Vector"));
+    }
+
+    #[test]
+    fn test_script_set() {
+        let japanese = set(
+            &["han", "hiragana", "katakana"],
+            Threshold {
+                dominant: true,
+                ..Default::default()
+            },
+        );
+        assert!(japanese.matches("日本語の歴史"));
+        assert!(japanese.matches("コーヒー 東京"));
+        assert!(!japanese.matches("東京 is the capital of Japan"));
+
+        let kana = set(&["hiragana", "katakana"], Threshold::default());
+        assert!(kana.matches("日本語の歴史"));
+        assert!(!kana.matches("汉语的历史"));
+    }
+
+    #[test]
+    fn test_route() {
+        let ortho: Ortho = toml::from_str(
+            r#"
+            default = "google"
+            scripts = [
+                { script = ["han", "hiragana", "katakana"], to = "google_jp", dominant = true, min_count = 2 },
+                { script = "han", to = "bing_cn", min_ratio = 0.5 },
+            ]
+            "#,
+        )
+        .unwrap();
+        let EngineNode::Ortho(ortho) = ortho.build("ortho".to_string()) else {
+            panic!("Expected an ortho engine");
+        };
+        assert_eq!(ortho.route("コーヒー"), "google_jp");
+        assert_eq!(ortho.route("你好"), "google_jp");
+        assert_eq!(ortho.route("Rust 中文教程"), "bing_cn");
+        assert_eq!(ortho.route("iPhone 发布会"), "google");
+
+        let single: Ortho = toml::from_str(
+            r#"
+            default = "google"
+            script = "han"
+            to = "bing_cn"
+            "#,
+        )
+        .unwrap();
+        let EngineNode::Ortho(single) = single.build("ortho".to_string()) else {
+            panic!("Expected an ortho engine");
+        };
+        assert_eq!(single.route("iPhone 发布会"), "bing_cn");
+    }
+}
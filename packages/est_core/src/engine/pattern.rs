@@ -0,0 +1,137 @@
+//! An engine that forwards to specified engines based on regular expressions over the query content.
+//!
+//! Rules are tried in order, and the first rule whose pattern matches decides the engine.
+//! The content can be rewritten with the captures of the match,
+//!   either by a `rewrite` template like `$number`, or implicitly by a capture group named `content`.
+use super::{Engine, EngineNode};
+use crate::{reaction::Forward, Instance, Query, Reaction};
+use regex::Regex;
+use std::future::Future;
+
+/// The name of the capture group that implicitly replaces the content.
+const CAPTURE_CONTENT: &str = "content";
+
+pub struct PatternRule {
+    regex: Regex,
+    to: String,
+    rewrite: Option<String>,
+}
+
+pub struct Pattern {
+    identifier: String,
+    default: String,
+    rules: Vec<PatternRule>,
+}
+
+impl PatternRule {
+    /// The content the query should be rewritten to, if the rule matches.
+    fn apply(&self, content: &str) -> Option<String> {
+        let captures = self.regex.captures(content)?;
+        let rewritten = if let Some(rewrite) = &self.rewrite {
+            let mut rewritten = String::new();
+            captures.expand(rewrite, &mut rewritten);
+            rewritten
+        } else if let Some(m) = captures.name(CAPTURE_CONTENT) {
+            m.as_str().to_string()
+        } else {
+            content.to_string()
+        };
+        Some(rewritten)
+    }
+}
+
+impl Engine for Pattern {
+    fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    fn react<'e, 'q: 'e, 'i: 'e>(
+        &'e self,
+        query: &'q Query,
+        _instance: &'i Instance,
+    ) -> impl Future<Output = Reaction> + Send + 'e {
+        let reaction = self
+            .rules
+            .iter()
+            .find_map(|rule| {
+                rule.apply(query.content())
+                    .map(|content| Forward::Rewrite(rule.to.clone(), 1, content))
+            })
+            .unwrap_or_else(|| Forward::Mention(self.default.clone(), 1));
+
+        async move { Ok(reaction.into()) }
+    }
+}
+
+impl From<Pattern> for EngineNode {
+    fn from(pattern: Pattern) -> Self {
+        Self::Pattern(pattern)
+    }
+}
+
+pub(crate) mod compose {
+    use regex::Regex;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug)]
+    pub(crate) struct PatternRule {
+        pub regex: String,
+        pub to: String,
+        #[serde(default)]
+        pub rewrite: Option<String>,
+    }
+
+    #[derive(Deserialize, Serialize, Debug)]
+    pub(crate) struct Pattern {
+        pub default: String,
+        pub patterns: Vec<PatternRule>,
+    }
+
+    fn get_rule(rule: PatternRule) -> super::PatternRule {
+        let PatternRule { regex, to, rewrite } = rule;
+        let regex = Regex::new(&regex)
+            .unwrap_or_else(|err| panic!("Invalid regex in pattern engine: {}", err));
+        super::PatternRule { regex, to, rewrite }
+    }
+
+    impl Pattern {
+        pub(crate) fn build(self, identifier: String) -> crate::engine::EngineNode {
+            super::Pattern {
+                identifier,
+                default: self.default,
+                rules: self.patterns.into_iter().map(get_rule).collect(),
+            }
+            .into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PatternRule;
+    use regex::Regex;
+
+    fn rule(regex: &str, rewrite: Option<&str>) -> PatternRule {
+        PatternRule {
+            regex: Regex::new(regex).unwrap(),
+            to: String::new(),
+            rewrite: rewrite.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_apply_rule() {
+        let issue = rule(r"^#(?<content>\d+)$", None);
+        assert_eq!(issue.apply("#1234").as_deref(), Some("1234"));
+        assert_eq!(issue.apply("#12a"), None);
+
+        let cve = rule(r"^CVE-\d{4}-\d{4,}$", None);
+        assert_eq!(cve.apply("CVE-2024-3094").as_deref(), Some("CVE-2024-3094"));
+
+        let doi = rule(r"^(?:doi:)?(?<doi>10\.\d{4,9}/\S+)$", Some("https://doi.org/$doi"));
+        assert_eq!(
+            doi.apply("doi:10.1000/182").as_deref(),
+            Some("https://doi.org/10.1000/182")
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! An engine that decides on the reaction by running a [Rhai](https://rhai.rs) script.
+//!
+//! The script is compiled once when the engine is built, and runs in a sandbox
+//!   without modules, printing or `eval`, and within limits on operations and sizes.
+//! It sees the query as the variables
+//! - `mention`, an array of the segments of the mention, `mention[0]` being the engine itself,
+//! - `content`, a string,
+//! - `scope`, a map from the keys of the scopes to their values, the unkeyed one under `""`,
+//!
+//! and evaluates to one of
+//! - `navigate(url)`,
+//! - `forward(id)`, which sends the query to another engine,
+//! - `forward(id, content)`, which also replaces the content,
+//! - `reject(reason)`, which declines the query so that a chain may try another engine.
+//!
+//! A script fails with `throw "reason"`, which stops a chain instead of passing the query on.
+use super::{Engine, EngineNode};
+use crate::reaction::{Forward, Navigate};
+use crate::{AcceptanceErr, Instance, Query, Reaction, ReactionErr};
+use rhai::{Array, Dynamic, EvalAltResult, Map, AST};
+use std::future::Future;
+
+/// Limits on the resources a script may use for each query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Limits {
+    pub max_operations: u64,
+    pub max_call_levels: usize,
+    pub max_expr_depth: usize,
+    pub max_string_size: usize,
+    pub max_array_size: usize,
+    pub max_map_size: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_operations: 100_000,
+            max_call_levels: 32,
+            max_expr_depth: 64,
+            max_string_size: 64 * 1024,
+            max_array_size: 1024,
+            max_map_size: 1024,
+        }
+    }
+}
+
+/// The decision a script evaluates to.
+#[derive(Clone, Debug)]
+enum Decision {
+    Navigate(String),
+    Forward(String),
+    Rewrite(String, String),
+    Reject(String),
+}
+
+pub struct Script {
+    identifier: String,
+    engine: Box<rhai::Engine>,
+    ast: AST,
+}
+
+/// Create a sandboxed Rhai engine with the functions available to scripts.
+fn sandbox(limits: &Limits) -> rhai::Engine {
+    let mut engine = rhai::Engine::new();
+    engine
+        .set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new())
+        .disable_symbol("eval")
+        .on_print(|_| {})
+        .on_debug(|_, _, _| {})
+        .set_max_operations(limits.max_operations)
+        .set_max_call_levels(limits.max_call_levels)
+        .set_max_expr_depths(limits.max_expr_depth, limits.max_expr_depth)
+        .set_max_string_size(limits.max_string_size)
+        .set_max_array_size(limits.max_array_size)
+        .set_max_map_size(limits.max_map_size);
+
+    engine
+        .register_type_with_name::<Decision>("Decision")
+        .register_fn("navigate", |url: &str| Decision::Navigate(url.to_string()))
+        .register_fn("forward", |id: &str| Decision::Forward(id.to_string()))
+        .register_fn("forward", |id: &str, content: &str| {
+            Decision::Rewrite(id.to_string(), content.to_string())
+        })
+        .register_fn("reject", |reason: &str| Decision::Reject(reason.to_string()));
+    engine
+}
+
+impl Script {
+    /// Compile the script, failing on syntax errors.
+    pub fn compile(identifier: String, source: &str, limits: &Limits) -> Result<Self, String> {
+        let engine = sandbox(limits);
+        let ast = engine.compile(source).map_err(|err| err.to_string())?;
+        Ok(Self {
+            identifier,
+            engine: Box::new(engine),
+            ast,
+        })
+    }
+
+    fn run(&self, query: &Query) -> Reaction {
+        let mut scope = rhai::Scope::new();
+        let mention: Array = query.mention.iter().cloned().map(Dynamic::from).collect();
+        let scopes: Map = query
+            .scope
+            .iter()
+            .map(|(key, value)| (key.into(), value.clone().into()))
+            .collect();
+        scope
+            .push_constant("mention", mention)
+            .push_constant("content", query.content().to_string())
+            .push_constant("scope", scopes);
+
+        let fail = |message: String| ReactionErr::Script {
+            id: self.identifier.clone(),
+            message,
+        };
+        let result = self
+            .engine
+            .eval_ast_with_scope::<Dynamic>(&mut scope, &self.ast)
+            .map_err(|err| match *err {
+                EvalAltResult::ErrorRuntime(thrown, _) => AcceptanceErr::Failed {
+                    id: self.identifier.clone(),
+                    reason: thrown.to_string(),
+                }
+                .into(),
+                err => fail(err.to_string()),
+            })?;
+        let type_name = result.type_name();
+        let decision = result
+            .try_cast::<Decision>()
+            .ok_or_else(|| fail(format!("expected a navigation, a forward or a rejection, got {type_name}")))?;
+
+        match decision {
+            Decision::Navigate(url) => Navigate::from_str(url, true),
+            Decision::Forward(id) => Ok(Forward::Mention(id, 1).into()),
+            Decision::Rewrite(id, content) => Ok(Forward::Rewrite(id, 1, content).into()),
+            Decision::Reject(reason) => Err(AcceptanceErr::Rejected {
+                id: self.identifier.clone(),
+                reason,
+            }
+            .into()),
+        }
+    }
+}
+
+impl Engine for Script {
+    fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    fn react<'e, 'q: 'e, 'i: 'e>(
+        &'e self,
+        query: &'q Query,
+        _instance: &'i Instance,
+    ) -> impl Future<Output = Reaction> + Send + 'e {
+        let reaction = self.run(query);
+
+        async move { reaction }
+    }
+}
+
+impl From<Script> for EngineNode {
+    fn from(script: Script) -> Self {
+        Self::Script(script)
+    }
+}
+
+pub(crate) mod compose {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug)]
+    pub(crate) struct Script {
+        pub script: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub max_operations: Option<u64>,
+    }
+
+    impl Script {
+        pub(crate) fn build(self, identifier: String) -> crate::engine::EngineNode {
+            let mut limits = super::Limits::default();
+            if let Some(max_operations) = self.max_operations {
+                limits.max_operations = max_operations;
+            }
+            super::Script::compile(identifier.clone(), &self.script, &limits)
+                .unwrap_or_else(|err| panic!("Invalid script in script engine `{}`: {}", identifier, err))
+                .into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Limits, Script};
+    use crate::{AcceptanceErr, Query, ReactionErr, ReactionVerb, reaction::Forward};
+
+    fn run(source: &str, query: &str) -> Result<ReactionVerb, ReactionErr> {
+        let script = Script::compile("script".to_string(), source, &Limits::default()).unwrap();
+        script.run(&query.parse::<Query>().unwrap())
+    }
+
+    #[test]
+    fn test_run() {
+        let source = r##"
+            if content.starts_with("#") {
+                forward("issue", content.sub_string(1))
+            } else if "lang" in scope {
+                navigate(`https://${scope.lang}.wikipedia.org/wiki/${content}`)
+            } else if mention.len() > 1 {
+                forward(mention[1])
+            } else {
+                throw "nothing to do";
+            }
+        "##;
+
+        let reaction = run(source, "#1234").unwrap();
+        assert!(matches!(reaction, ReactionVerb::Forward(Forward::Rewrite(id, 1, content)) if id == "issue" && content == "1234"));
+
+        let ReactionVerb::Navigate(nav) = run(source, "Rust !lang=en").unwrap() else {
+            panic!("Expected a navigation");
+        };
+        assert_eq!(nav.url().as_str(), "https://en.wikipedia.org/wiki/Rust");
+
+        let reaction = run(source, "@script.bing rust").unwrap();
+        assert!(matches!(reaction, ReactionVerb::Forward(Forward::Mention(id, 1)) if id == "bing"));
+
+        let err = run(source, "rust").unwrap_err();
+        assert!(
+            matches!(&err, ReactionErr::NotAccepted(AcceptanceErr::Failed { reason, .. }) if reason == "nothing to do"),
+            "{err:?}"
+        );
+        assert!(!err.is_recoverable());
+
+        let err = run(r#"reject("not indexed")"#, "rust").unwrap_err();
+        assert_eq!(err.to_string(), "Query not accepted: `script` rejected the query: not indexed.");
+        assert!(err.is_recoverable());
+    }
+
+    #[test]
+    fn test_sandbox() {
+        assert!(matches!(run("loop {}", ""), Err(ReactionErr::Script { .. })));
+        assert!(matches!(run("42", ""), Err(ReactionErr::Script { .. })));
+        assert!(run(r#"import "std" as std; navigate("https://example.com")"#, "").is_err());
+        assert!(Script::compile("script".to_string(), "eval(\"1\")", &Limits::default()).is_err());
+        assert!(Script::compile("script".to_string(), "navigate(", &Limits::default()).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+//! An engine that forwards to specified engines by rules over the whole query.
+//!
+//! Rules are tried in order, and the first rule whose predicate holds decides the engine.
+//! Predicates look at the content, its scripts, the scopes, the mention tail and the time of day,
+//!   and combine with `all`, `any` and `not`.
+use super::{Engine, EngineNode, ortho::ScriptSet};
+use crate::{Instance, Query, Reaction, reaction::Forward};
+use chrono::{DateTime, FixedOffset, Local, NaiveTime, Utc};
+use regex::Regex;
+use std::future::Future;
+
+pub enum Predicate {
+    /// The number of chars of the content is within the range.
+    Length { min: usize, max: usize },
+    Regex(Regex),
+    /// The content is in the scripts as much as the set requires, as in an ortho engine.
+    Script(ScriptSet),
+    /// The scope is given, with the value if any.
+    Scope { key: String, value: Option<String> },
+    /// The number of mention segments after the id is within the range,
+    ///   and each of them matches the regex if any.
+    MentionTail { min: usize, max: usize, regex: Option<Regex> },
+    /// The time of day is in `[from, to)`, which wraps around midnight if `to` is earlier,
+    ///   at the offset or else in the local time zone.
+    Time {
+        from: NaiveTime,
+        to: NaiveTime,
+        offset: Option<FixedOffset>,
+    },
+    All(Vec<Predicate>),
+    Any(Vec<Predicate>),
+    Not(Box<Predicate>),
+}
+
+pub struct SwitchRule {
+    when: Predicate,
+    to: String,
+}
+
+pub struct Switch {
+    identifier: String,
+    default: String,
+    rules: Vec<SwitchRule>,
+}
+
+impl Predicate {
+    fn eval(&self, query: &Query, now: DateTime<Utc>) -> bool {
+        match self {
+            Self::Length { min, max } => (*min..=*max).contains(&query.content.chars().count()),
+            Self::Regex(regex) => regex.is_match(&query.content),
+            Self::Script(scripts) => scripts.matches(&query.content),
+            Self::Scope { key, value } => match (query.scope(key), value.as_deref()) {
+                (Some(given), Some(value)) => given == value,
+                (given, None) => given.is_some(),
+                (None, Some(_)) => false,
+            },
+            Self::MentionTail { min, max, regex } => {
+                let tail = query.mention_tail();
+                (*min..=*max).contains(&tail.len())
+                    && regex
+                        .as_ref()
+                        .is_none_or(|regex| tail.iter().all(|segment| regex.is_match(segment)))
+            }
+            Self::Time { from, to, offset } => {
+                let time = match offset {
+                    Some(offset) => now.with_timezone(offset).time(),
+                    None => now.with_timezone(&Local).time(),
+                };
+                if from <= to {
+                    *from <= time && time < *to
+                } else {
+                    *from <= time || time < *to
+                }
+            }
+            Self::All(predicates) => predicates.iter().all(|p| p.eval(query, now)),
+            Self::Any(predicates) => predicates.iter().any(|p| p.eval(query, now)),
+            Self::Not(predicate) => !predicate.eval(query, now),
+        }
+    }
+}
+
+impl Switch {
+    /// The engine the query should be forwarded to at the time.
+    fn route(&self, query: &Query, now: DateTime<Utc>) -> &str {
+        self.rules
+            .iter()
+            .find(|rule| rule.when.eval(query, now))
+            .map(|rule| rule.to.as_str())
+            .unwrap_or(&self.default)
+    }
+}
+
+impl Engine for Switch {
+    fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    fn react<'e, 'q: 'e, 'i: 'e>(
+        &'e self,
+        query: &'q Query,
+        _instance: &'i Instance,
+    ) -> impl Future<Output = Reaction> + Send + 'e {
+        let reaction = Forward::Mention(self.route(query, Utc::now()).to_owned(), 1);
+
+        async move { Ok(reaction.into()) }
+    }
+}
+
+impl From<Switch> for EngineNode {
+    fn from(switch: Switch) -> Self {
+        Self::Switch(switch)
+    }
+}
+
+pub(crate) mod compose {
+    use crate::engine::ortho::compose::{ScriptNames, Threshold, get_script_set};
+    use chrono::{FixedOffset, NaiveTime};
+    use regex::Regex;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize, Serialize, Debug)]
+    #[serde(rename_all = "kebab-case")]
+    pub(crate) enum Predicate {
+        Length {
+            #[serde(default)]
+            min: Option<usize>,
+            #[serde(default)]
+            max: Option<usize>,
+        },
+        Regex(String),
+        Script {
+            name: ScriptNames,
+            #[serde(flatten)]
+            threshold: Threshold,
+        },
+        Scope {
+            /// The key of the scope, an unkeyed scope like `!week` by default.
+            #[serde(default)]
+            key: String,
+            #[serde(default)]
+            value: Option<String>,
+        },
+        MentionTail {
+            #[serde(default)]
+            min: Option<usize>,
+            #[serde(default)]
+            max: Option<usize>,
+            #[serde(default)]
+            regex: Option<String>,
+        },
+        Time {
+            from: String,
+            to: String,
+            /// A UTC offset like `+08:00`, the local time zone by default.
+            #[serde(default)]
+            offset: Option<String>,
+        },
+        All(Vec<Predicate>),
+        Any(Vec<Predicate>),
+        Not(Box<Predicate>),
+    }
+
+    #[derive(Deserialize, Serialize, Debug)]
+    pub(crate) struct SwitchRule {
+        pub when: Predicate,
+        pub to: String,
+    }
+
+    #[derive(Deserialize, Serialize, Debug)]
+    pub(crate) struct Switch {
+        pub default: String,
+        pub rules: Vec<SwitchRule>,
+    }
+
+    fn get_regex(regex: &str) -> Regex {
+        Regex::new(regex).unwrap_or_else(|err| panic!("Invalid regex in switch engine: {}", err))
+    }
+
+    fn get_time(time: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(time, "%H:%M")
+            .unwrap_or_else(|err| panic!("Invalid time in switch engine: {}: {}", time, err))
+    }
+
+    impl Predicate {
+        fn build(self) -> super::Predicate {
+            match self {
+                Self::Length { min, max } => super::Predicate::Length {
+                    min: min.unwrap_or(0),
+                    max: max.unwrap_or(usize::MAX),
+                },
+                Self::Regex(regex) => super::Predicate::Regex(get_regex(&regex)),
+                Self::Script { name, threshold } => {
+                    super::Predicate::Script(get_script_set(name, threshold, "switch"))
+                }
+                Self::Scope { key, value } => super::Predicate::Scope { key, value },
+                Self::MentionTail { min, max, regex } => super::Predicate::MentionTail {
+                    min: min.unwrap_or(0),
+                    max: max.unwrap_or(usize::MAX),
+                    regex: regex.as_deref().map(get_regex),
+                },
+                Self::Time { from, to, offset } => super::Predicate::Time {
+                    from: get_time(&from),
+                    to: get_time(&to),
+                    offset: offset.map(|offset| {
+                        offset.parse::<FixedOffset>().unwrap_or_else(|err| {
+                            panic!("Invalid UTC offset in switch engine: {}: {}", offset, err)
+                        })
+                    }),
+                },
+                Self::All(predicates) => super::Predicate::All(predicates.into_iter().map(Self::build).collect()),
+                Self::Any(predicates) => super::Predicate::Any(predicates.into_iter().map(Self::build).collect()),
+                Self::Not(predicate) => super::Predicate::Not(Box::new(predicate.build())),
+            }
+        }
+    }
+
+    impl Switch {
+        pub(crate) fn build(self, identifier: String) -> crate::engine::EngineNode {
+            super::Switch {
+                identifier,
+                default: self.default,
+                rules: self
+                    .rules
+                    .into_iter()
+                    .map(|rule| super::SwitchRule {
+                        when: rule.when.build(),
+                        to: rule.to,
+                    })
+                    .collect(),
+            }
+            .into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::compose;
+    use crate::{Query, engine::EngineNode};
+    use chrono::{DateTime, Utc};
+
+    const SWITCH: &str = r#"
+        default = "google"
+        rules = [
+            { when = { regex = '\?$' }, to = "answer" },
+            { when = { all = [{ length = { max = 8 } }, { script = { name = "han", min_ratio = 0.5 } }] }, to = "bing_cn" },
+            { when = { scope = { key = "site" } }, to = "site" },
+            { when = { any = [{ scope = { value = "week" } }, { not = { mention-tail = { max = 0 } } }] }, to = "news" },
+            { when = { time = { from = "22:00", to = "06:00", offset = "+08:00" } }, to = "night" },
+        ]
+    "#;
+
+    fn route(query: &str, now: &str) -> String {
+        let switch: compose::Switch = toml::from_str(SWITCH).unwrap();
+        let EngineNode::Switch(switch) = switch.build("switch".to_string()) else {
+            panic!("Expected a switch engine");
+        };
+        let query: Query = query.parse().unwrap();
+        let now: DateTime<Utc> = now.parse().unwrap();
+        switch.route(&query, now).to_string()
+    }
+
+    #[test]
+    fn test_route() {
+        const NOON: &str = "2025-01-01T04:00:00Z";
+        assert_eq!(route("@switch what is rust?", NOON), "answer");
+        assert_eq!(route("@switch 你好 ok", NOON), "bing_cn");
+        assert_eq!(route("@switch 你 rust", NOON), "google");
+        assert_eq!(route("@switch 你好 rust programming", NOON), "google");
+        assert_eq!(route("@switch 你好 rust programming !site=github.com", NOON), "site");
+        assert_eq!(route("@switch rust !week", NOON), "news");
+        assert_eq!(route("@switch.today rust", NOON), "news");
+        assert_eq!(route("@switch rust", NOON), "google");
+        assert_eq!(route("@switch rust", "2025-01-01T15:00:00Z"), "night");
+        assert_eq!(route("@switch rust", "2025-01-01T22:00:00Z"), "google");
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid time in switch engine")]
+    fn test_invalid_time() {
+        let switch: compose::Switch = toml::from_str(
+            r#"
+            default = "google"
+            rules = [{ when = { time = { from = "25:00", to = "06:00" } }, to = "night" }]
+            "#,
+        )
+        .unwrap();
+        switch.build("switch".to_string());
+    }
+}
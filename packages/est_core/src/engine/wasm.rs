@@ -0,0 +1,393 @@
+//! An engine that runs a WebAssembly plugin to react to the query.
+//!
+//! The module is compiled once when the engine is built, and each query is handled
+//!   by a fresh instance of it, with limited fuel and memory.
+//! Plugins have no access to the host beyond the functions they are allowed to import.
+//! See `ABI.md` of the `est_plugin` crate for the interface a plugin implements.
+use super::{Engine, EngineNode};
+use crate::reaction::{Forward, Navigate, Text};
+use crate::{AcceptanceErr, Instance, Query, Reaction, ReactionErr};
+use est_plugin::{ABI_VERSION, Request, Response};
+use std::future::Future;
+use wasmi::{Caller, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+/// The module plugins import host functions from.
+const HOST_MODULE: &str = "est";
+
+/// Host functions a plugin may be allowed to import.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HostFunction {
+    /// Log a message through the logger of the host.
+    Log,
+}
+
+impl HostFunction {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Log => "log",
+        }
+    }
+}
+
+/// Resources a plugin may use for each query.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Limits {
+    pub fuel: u64,
+    pub max_memory: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            fuel: 10_000_000,
+            max_memory: 16 * 1024 * 1024,
+        }
+    }
+}
+
+pub struct Wasm {
+    identifier: String,
+    module: Module,
+    linker: Linker<StoreLimits>,
+    limits: Limits,
+    config: serde_json::Value,
+}
+
+impl Wasm {
+    /// Compile a plugin, checking that it imports only the allowed host functions.
+    pub fn compile(
+        identifier: String,
+        wasm: &[u8],
+        allow: &[HostFunction],
+        limits: Limits,
+        config: serde_json::Value,
+    ) -> Result<Self, String> {
+        let mut engine_config = wasmi::Config::default();
+        engine_config.consume_fuel(true);
+        let engine = wasmi::Engine::new(&engine_config);
+        let module = Module::new(&engine, wasm).map_err(|err| err.to_string())?;
+
+        for import in module.imports() {
+            let is_allowed = import.module() == HOST_MODULE
+                && allow.iter().any(|function| function.name() == import.name());
+            if !is_allowed {
+                return Err(format!(
+                    "the import `{}.{}` is not allowed",
+                    import.module(),
+                    import.name()
+                ));
+            }
+        }
+
+        let mut linker = Linker::new(&engine);
+        if allow.contains(&HostFunction::Log) {
+            let id = identifier.clone();
+            linker
+                .func_wrap(HOST_MODULE, HostFunction::Log.name(), move |caller: Caller<'_, StoreLimits>, ptr: i32, len: i32| {
+                    let memory = caller.get_export("memory").and_then(|export| export.into_memory());
+                    let message = memory.and_then(|memory| {
+                        let data = memory.data(&caller);
+                        data.get(ptr as u32 as usize..)?.get(..len as u32 as usize)
+                    });
+                    if let Some(message) = message {
+                        log::info!("[{id}] {}", String::from_utf8_lossy(message));
+                    }
+                })
+                .map_err(|err| err.to_string())?;
+        }
+
+        let plugin = Self {
+            identifier,
+            module,
+            linker,
+            limits,
+            config,
+        };
+        let version = plugin.instantiate().and_then(|(mut store, instance)| {
+            instance
+                .get_typed_func::<(), i32>(&store, "est_abi_version")
+                .and_then(|func| func.call(&mut store, ()))
+                .map_err(|err| err.to_string())
+        })?;
+        if version != ABI_VERSION {
+            return Err(format!("ABI version {version} is not supported, expected {ABI_VERSION}"));
+        }
+        Ok(plugin)
+    }
+
+    fn instantiate(&self) -> Result<(Store<StoreLimits>, wasmi::Instance), String> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.limits.max_memory)
+            .build();
+        let mut store = Store::new(self.module.engine(), limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.limits.fuel).map_err(|err| err.to_string())?;
+        let instance = self
+            .linker
+            .instantiate(&mut store, &self.module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|err| err.to_string())?;
+        Ok((store, instance))
+    }
+
+    /// Send a request to a fresh instance of the plugin.
+    fn call(&self, request: &[u8]) -> Result<Response, String> {
+        let (mut store, instance) = self.instantiate()?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or("the plugin exports no memory")?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, "est_alloc")
+            .map_err(|err| err.to_string())?;
+        let react = instance
+            .get_typed_func::<(i32, i32), i64>(&store, "est_react")
+            .map_err(|err| err.to_string())?;
+
+        let len = i32::try_from(request.len()).map_err(|_| "the request is too large")?;
+        let ptr = alloc.call(&mut store, len).map_err(|err| err.to_string())?;
+        memory
+            .write(&mut store, ptr as u32 as usize, request)
+            .map_err(|err| err.to_string())?;
+        let result = react.call(&mut store, (ptr, len)).map_err(|err| err.to_string())? as u64;
+
+        let (ptr, len) = ((result >> 32) as usize, (result & 0xFFFF_FFFF) as usize);
+        let response = memory
+            .data(&store)
+            .get(ptr..)
+            .and_then(|data| data.get(..len))
+            .ok_or("the response is out of bounds")?;
+        serde_json::from_slice(response).map_err(|err| format!("invalid response: {err}"))
+    }
+
+    fn run(&self, query: &Query) -> Reaction {
+        let fail = |message: String| ReactionErr::Plugin {
+            id: self.identifier.clone(),
+            message,
+        };
+
+        let request = Request {
+            mention: query.mention.to_vec(),
+            content: query.content().to_string(),
+            scope: query.scope.iter().map(|(key, value)| (key.clone(), value.clone())).collect(),
+            config: self.config.clone(),
+        };
+        let request = serde_json::to_vec(&request).map_err(|err| fail(err.to_string()))?;
+
+        match self.call(&request).map_err(fail)? {
+            Response::Navigate(url) => Navigate::from_str(url, true),
+            Response::Forward { to, content: None } => Ok(Forward::Mention(to, 1).into()),
+            Response::Forward { to, content: Some(content) } => Ok(Forward::Rewrite(to, 1, content).into()),
+            Response::Text(text) => Ok(Text::new(text).into()),
+            Response::Reject(reason) => Err(AcceptanceErr::Rejected {
+                id: self.identifier.clone(),
+                reason,
+            }
+            .into()),
+            Response::Error(message) => Err(fail(message)),
+        }
+    }
+}
+
+impl Engine for Wasm {
+    fn identifier(&self) -> &str {
+        &self.identifier
+    }
+
+    fn react<'e, 'q: 'e, 'i: 'e>(
+        &'e self,
+        query: &'q Query,
+        _instance: &'i Instance,
+    ) -> impl Future<Output = Reaction> + Send + 'e {
+        let reaction = self.run(query);
+
+        async move { reaction }
+    }
+}
+
+impl From<Wasm> for EngineNode {
+    fn from(wasm: Wasm) -> Self {
+        Self::Wasm(wasm)
+    }
+}
+
+pub(crate) mod compose {
+    use super::HostFunction;
+    use serde::{Deserialize, Serialize};
+    use std::path::PathBuf;
+
+    #[derive(Deserialize, Serialize, Debug)]
+    pub(crate) struct Wasm {
+        /// Path to the module, relative to the working directory.
+        pub path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub fuel: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub max_memory: Option<usize>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        pub allow: Vec<HostFunction>,
+        #[serde(default)]
+        pub config: serde_json::Value,
+    }
+
+    impl Wasm {
+        pub(crate) fn build(self, identifier: String) -> crate::engine::EngineNode {
+            let wasm = std::fs::read(&self.path).unwrap_or_else(|err| {
+                panic!("Cannot read plugin of wasm engine `{}` at {}: {}", identifier, self.path.display(), err)
+            });
+            let mut limits = super::Limits::default();
+            if let Some(fuel) = self.fuel {
+                limits.fuel = fuel;
+            }
+            if let Some(max_memory) = self.max_memory {
+                limits.max_memory = max_memory;
+            }
+            super::Wasm::compile(identifier.clone(), &wasm, &self.allow, limits, self.config)
+                .unwrap_or_else(|err| panic!("Invalid plugin of wasm engine `{}`: {}", identifier, err))
+                .into()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{HostFunction, Limits, Wasm};
+    use crate::{AcceptanceErr, Instance, Query, ReactionErr, ReactionVerb, compose::Compose, reaction::Forward};
+    use futures::executor::block_on;
+
+    /// A plugin that answers every request with the response in its data segment,
+    ///   optionally after looping forever or logging.
+    fn plugin(response: &str, body: &str, imports: &str) -> Vec<u8> {
+        let escaped = response.replace('\\', "\\\\").replace('"', "\\\"");
+        wat::parse_str(format!(
+            r#"(module
+                {imports}
+                (memory (export "memory") 1)
+                (data (i32.const 0) "{escaped}")
+                (global $next (mut i32) (i32.const 1024))
+                (func (export "est_abi_version") (result i32) (i32.const 1))
+                (func (export "est_alloc") (param $len i32) (result i32)
+                    (local $ptr i32)
+                    (local.set $ptr (global.get $next))
+                    (global.set $next (i32.add (global.get $next) (local.get $len)))
+                    (local.get $ptr))
+                (func (export "est_react") (param $ptr i32) (param $len i32) (result i64)
+                    {body}
+                    (i64.const {len})))"#,
+            len = response.len(),
+        ))
+        .unwrap()
+    }
+
+    fn compile(wasm: &[u8], allow: &[HostFunction]) -> Result<Wasm, String> {
+        Wasm::compile("plugin".to_string(), wasm, allow, Limits::default(), serde_json::Value::Null)
+    }
+
+    fn run(wasm: &[u8], query: &str) -> Result<ReactionVerb, ReactionErr> {
+        compile(wasm, &[]).unwrap().run(&query.parse::<Query>().unwrap())
+    }
+
+    #[test]
+    fn test_run() {
+        let reaction = run(&plugin(r#"{"forward":{"to":"issue","content":"42"}}"#, "", ""), "#42").unwrap();
+        assert!(matches!(reaction, ReactionVerb::Forward(Forward::Rewrite(id, 1, content)) if id == "issue" && content == "42"));
+
+        let ReactionVerb::Navigate(nav) = run(&plugin(r#"{"navigate":"https://example.com/"}"#, "", ""), "").unwrap() else {
+            panic!("Expected a navigation");
+        };
+        assert_eq!(nav.url().as_str(), "https://example.com/");
+
+        let err = run(&plugin(r#"{"error":"no"}"#, "", ""), "").unwrap_err();
+        assert!(matches!(&err, ReactionErr::Plugin { message, .. } if message == "no"), "{err:?}");
+        let err = run(&plugin(r#"{"reject":"no"}"#, "", ""), "").unwrap_err();
+        assert!(
+            matches!(&err, ReactionErr::NotAccepted(AcceptanceErr::Rejected { reason, .. }) if reason == "no"),
+            "{err:?}"
+        );
+        assert!(run(&plugin("not json", "", ""), "").is_err());
+    }
+
+    #[test]
+    fn test_chain() {
+        let dir = std::env::temp_dir();
+        let rejecting = dir.join(format!("est-rejecting-{}.wasm", std::process::id()));
+        let failing = dir.join(format!("est-failing-{}.wasm", std::process::id()));
+        std::fs::write(&rejecting, plugin(r#"{"reject":"not mine"}"#, "", "")).unwrap();
+        std::fs::write(&failing, plugin(r#"{"error":"broken"}"#, "", "")).unwrap();
+        let compose = format!(
+            r#"
+            default = "google"
+
+            [[engines]]
+            id = "google"
+            type = "cloze"
+            template = "https://google.com/search?q={{}}"
+
+            [[engines]]
+            id = "rejecting"
+            type = "wasm"
+            path = {rejecting:?}
+
+            [[engines]]
+            id = "failing"
+            type = "wasm"
+            path = {failing:?}
+
+            [[engines]]
+            id = "lenient"
+            type = "chain"
+            targets = ["rejecting", "google"]
+
+            [[engines]]
+            id = "strict"
+            type = "chain"
+            targets = ["failing", "google"]
+            "#,
+            rejecting = rejecting.display().to_string(),
+            failing = failing.display().to_string(),
+        );
+        let instance: Instance = toml::from_str::<Compose>(&compose).unwrap().into();
+        std::fs::remove_file(&rejecting).unwrap();
+        std::fs::remove_file(&failing).unwrap();
+
+        let react = |query: &str| block_on(instance.react(instance.parse(query).unwrap()));
+        let Ok(ReactionVerb::Navigate(nav)) = react("@lenient rust") else {
+            panic!("Expected the chain to go on to google");
+        };
+        assert_eq!(nav.url().as_str(), "https://google.com/search?q=rust");
+        let err = react("@strict rust").unwrap_err();
+        assert!(matches!(&err, ReactionErr::Plugin { message, .. } if message == "broken"), "{err:?}");
+    }
+
+    #[test]
+    fn test_limits() {
+        let spin = plugin(r#"{"text":""}"#, "(loop $spin (br $spin))", "");
+        assert!(matches!(run(&spin, ""), Err(ReactionErr::Plugin { .. })));
+
+        let grow = plugin(r#"{"text":""}"#, "(drop (memory.grow (i32.const 1024)))", "");
+        let wasm = Wasm::compile(
+            "plugin".to_string(),
+            &grow,
+            &[],
+            Limits { max_memory: 1 << 20, ..Default::default() },
+            serde_json::Value::Null,
+        )
+        .unwrap();
+        // Failing to grow returns -1 to the guest, which still answers.
+        assert!(wasm.run(&Query::default()).is_ok());
+    }
+
+    #[test]
+    fn test_imports() {
+        let log = plugin(
+            r#"{"text":"logged"}"#,
+            "(call $log (i32.const 0) (i32.const 4))",
+            r#"(import "est" "log" (func $log (param i32 i32)))"#,
+        );
+        assert!(compile(&log, &[]).is_err());
+        assert!(compile(&log, &[HostFunction::Log]).is_ok());
+
+        let wasi = plugin("", "", r#"(import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32)))"#);
+        assert!(compile(&wasi, &[HostFunction::Log]).is_err());
+    }
+}
@@ -0,0 +1,303 @@
+//! Importers of engines from the collections of other tools.
+//!
+//! Every importer turns the search engines it finds into cloze engines,
+//!   which serialize as the `[[engines]]` of a compose file to paste into a config.
+//! Imported engines give way to the engines of the compose file on any id or shorthand they share.
+//!
+//! Bangs in JSON are always imported, while YAML bangs, OpenSearch descriptions and browser profiles
+//!   need the `yaml`, `opensearch` and `browsers` features.
+use crate::engine::compose::{Engine, Shorthand};
+#[cfg(feature = "opensearch")]
+use crate::engine::{
+    cloze::compose::{Cloze, ClozeTemplate},
+    compose::{EngineSpec, EngineType},
+};
+#[cfg(feature = "browsers")]
+use crate::query::is_identifier;
+use serde::{Deserialize, Serialize};
+#[cfg(any(feature = "bookmarks", feature = "opensearch"))]
+use std::path::Path;
+use std::{collections::HashSet, path::PathBuf};
+use thiserror::Error;
+
+pub mod bangs;
+#[cfg(feature = "browsers")]
+pub mod chrome;
+#[cfg(feature = "browsers")]
+pub mod firefox;
+#[cfg(feature = "opensearch")]
+pub mod opensearch;
+
+/// Collections to import engines from, as the `[import]` table of a compose file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImportOptions {
+    /// A DuckDuckGo `bang.js` or Kagi bangs file, see [`bangs`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bangs: Option<PathBuf>,
+}
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("Cannot read {}: {source}", path.display())]
+    Io { path: PathBuf, source: std::io::Error },
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid YAML: {0}")]
+    #[cfg(feature = "yaml")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("Invalid XML: {0}")]
+    #[cfg(feature = "opensearch")]
+    Xml(#[from] quick_xml::Error),
+    #[error("Cannot query the database: {0}")]
+    #[cfg(feature = "browsers")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Invalid file: {0}")]
+    Invalid(String),
+}
+
+/// Engines imported from another tool.
+/// They serialize as the `[[engines]]` of a compose file.
+#[derive(Debug, Default, Serialize)]
+pub struct Imported {
+    pub(crate) engines: Vec<Engine>,
+    #[serde(skip)]
+    ids: HashSet<String>,
+    #[serde(skip)]
+    shorthands: HashSet<String>,
+}
+
+impl Imported {
+    pub fn len(&self) -> usize {
+        self.engines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.engines.is_empty()
+    }
+
+    /// Add an engine, numbering its id if it is already taken by another imported engine,
+    ///   and dropping the shorthands already taken with a warning.
+    fn push(&mut self, mut engine: Engine) {
+        let id = engine.id.clone();
+        let mut n = 1;
+        while self.ids.contains(&engine.id) {
+            n += 1;
+            engine.id = format!("{id}_{n}");
+        }
+        self.ids.insert(engine.id.clone());
+
+        let shorthand = match std::mem::take(&mut engine.shorthand) {
+            Shorthand::Single(shorthand) => vec![shorthand],
+            Shorthand::Multiple(shorthand) => shorthand,
+        };
+        let shorthand = shorthand
+            .into_iter()
+            .filter(|shorthand| {
+                let is_free = self.shorthands.insert(shorthand.clone());
+                if !is_free {
+                    log::warn!("Dropping the shorthand `{shorthand}` of `{}`, which is already taken", engine.id);
+                }
+                is_free
+            })
+            .collect();
+        engine.shorthand = Shorthand::Multiple(shorthand);
+        self.engines.push(engine);
+    }
+
+    #[cfg(feature = "opensearch")]
+    fn push_cloze(
+        &mut self,
+        id: String,
+        template: String,
+        shorthand: Vec<String>,
+        description: Option<String>,
+    ) {
+        self.push(Engine {
+            id,
+            engine: EngineSpec::Builtin(EngineType::Cloze(Cloze {
+                template: ClozeTemplate::Single(template),
+                args: Vec::new(),
+            })),
+            shorthand: Shorthand::Multiple(shorthand),
+            description,
+            category: None,
+            icon: None,
+        });
+    }
+}
+
+impl Extend<Engine> for Imported {
+    fn extend<T: IntoIterator<Item = Engine>>(&mut self, iter: T) {
+        for engine in iter {
+            self.push(engine);
+        }
+    }
+}
+
+impl IntoIterator for Imported {
+    type Item = Engine;
+    type IntoIter = std::vec::IntoIter<Engine>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.engines.into_iter()
+    }
+}
+
+impl ImportOptions {
+    /// Load the engines of every collection.
+    pub(crate) fn load(&self) -> Result<Imported, ImportError> {
+        let mut imported = Imported::default();
+        if let Some(path) = &self.bangs {
+            imported.extend(bangs::load(path)?);
+        }
+        Ok(imported)
+    }
+}
+
+#[cfg(any(feature = "bookmarks", feature = "opensearch"))]
+pub(crate) fn read(path: &Path) -> Result<Vec<u8>, ImportError> {
+    std::fs::read(path).map_err(|source| ImportError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// The header of Mozilla's LZ4 files, followed by the decompressed size and an LZ4 block.
+#[cfg(any(feature = "bookmarks", feature = "browsers"))]
+const MOZLZ4_MAGIC: &[u8] = b"mozLz40\0";
+
+/// Decompress a `.mozlz4` file, or return any other file as is.
+#[cfg(any(feature = "bookmarks", feature = "browsers"))]
+pub(crate) fn decompress(bytes: &[u8]) -> Result<Vec<u8>, ImportError> {
+    let Some(rest) = bytes.strip_prefix(MOZLZ4_MAGIC) else {
+        return Ok(bytes.to_vec());
+    };
+    let (size, block) = rest
+        .split_first_chunk::<4>()
+        .ok_or_else(|| ImportError::Invalid("truncated mozlz4 header".to_string()))?;
+    lz4_flex::block::decompress(block, u32::from_le_bytes(*size) as usize)
+        .map_err(|err| ImportError::Invalid(format!("invalid mozlz4 block: {err}")))
+}
+
+/// Open a database of a browser read-only, even while the browser holds a lock on it.
+#[cfg(feature = "browsers")]
+fn open_database(path: &Path) -> Result<rusqlite::Connection, ImportError> {
+    use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
+    use rusqlite::OpenFlags;
+
+    const URI_PATH: &AsciiSet = &CONTROLS.add(b' ').add(b'#').add(b'%').add(b'?');
+    if !path.is_file() {
+        return Err(ImportError::Io {
+            path: path.to_path_buf(),
+            source: std::io::ErrorKind::NotFound.into(),
+        });
+    }
+    let uri = format!(
+        "file:{}?immutable=1",
+        utf8_percent_encode(&path.to_string_lossy(), URI_PATH)
+    );
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI;
+    Ok(rusqlite::Connection::open_with_flags(uri, flags)?)
+}
+
+/// An id for an engine known by its name, such as `wikipedia_en` for `Wikipedia (en)`.
+#[cfg(feature = "opensearch")]
+fn identifier(name: &str) -> String {
+    let id = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("_");
+    match id.chars().next() {
+        Some(c) if !c.is_numeric() => id,
+        _ => format!("engine_{id}"),
+    }
+}
+
+/// A shorthand from the keyword of a browser, without the `@` Firefox prefixes its aliases with.
+/// Keywords that cannot be mentioned, like the `google.com` Chrome defaults to, are dropped with a warning.
+#[cfg(feature = "browsers")]
+fn shorthand(keyword: &str) -> Option<String> {
+    let keyword = keyword.trim().trim_start_matches('@');
+    if keyword.is_empty() {
+        return None;
+    }
+    if !is_identifier(keyword) {
+        log::warn!("Dropping the keyword `{keyword}`, which cannot be mentioned");
+        return None;
+    }
+    Some(keyword.to_string())
+}
+
+/// Escape the braces of a url for a template.
+#[cfg(feature = "opensearch")]
+fn escape(url: &str) -> String {
+    url.replace('{', "{{").replace('}', "}}")
+}
+
+/// Turn an OpenSearch url template into a template.
+/// `{searchTerms}` is the query, the encodings are UTF-8,
+///   and the other parameters, including browser-specific ones, are left empty.
+#[cfg(feature = "opensearch")]
+fn opensearch_template(url: &str) -> String {
+    let mut template = String::new();
+    let mut rest = url;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        template.push_str(&escape(&rest[..start]));
+        match rest[start + 1..start + len].trim_end_matches('?') {
+            "searchTerms" => template.push_str("{}"),
+            "inputEncoding" | "outputEncoding" => template.push_str("UTF-8"),
+            "language" => template.push('*'),
+            "google:baseURL" => template.push_str("https://www.google.com/"),
+            _ => {}
+        }
+        rest = &rest[start + len + 1..];
+    }
+    template.push_str(&escape(rest));
+    template
+}
+
+#[cfg(all(test, feature = "browsers"))]
+mod test {
+    use super::{identifier, opensearch_template, shorthand};
+
+    #[test]
+    fn test_opensearch_template() {
+        for (url, template) in [
+            (
+                "https://example.com/?q={searchTerms}",
+                "https://example.com/?q={}",
+            ),
+            (
+                "https://example.com/?q={searchTerms}&ie={inputEncoding}&p={startPage?}",
+                "https://example.com/?q={}&ie=UTF-8&p=",
+            ),
+            (
+                "{google:baseURL}search?q={searchTerms}",
+                "https://www.google.com/search?q={}",
+            ),
+            (
+                "https://example.com/?q={searchTerms}&x=}{",
+                "https://example.com/?q={}&x=}}{{",
+            ),
+        ] {
+            assert_eq!(opensearch_template(url), template, "url: {url}");
+        }
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(identifier("Wikipedia (en)"), "wikipedia_en");
+        assert_eq!(identifier("维基百科"), "维基百科");
+        assert_eq!(identifier("9gag"), "engine_9gag");
+        assert_eq!(shorthand("@google").as_deref(), Some("google"));
+        assert_eq!(shorthand(" "), None);
+        assert_eq!(shorthand("google.com"), None);
+        assert_eq!(shorthand("9gag"), None);
+    }
+}
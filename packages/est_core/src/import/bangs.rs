@@ -0,0 +1,197 @@
+//! Bangs of DuckDuckGo and Kagi as cloze engines.
+//!
+//! A local copy of DuckDuckGo's `bang.js`, or of Kagi's bangs in JSON or YAML, lists bangs like
+//!   `{ "s": "Wikipedia", "t": "w", "ts": ["wiki"], "u": "https://en.wikipedia.org/wiki/{{{s}}}", "c": "Research" }`,
+//!   where `{{{s}}}` stands for the query.
+//! Each bang becomes a cloze engine with the id `!w`, mentioned by its triggers as shorthands,
+//!   described by its name, and with its category.
+//! Bangs with relative urls, which refer to the search engine itself, are skipped,
+//!   and so are triggers that cannot be mentioned, like `9gag` or `g+`, with a warning of how many.
+use super::{ImportError, Imported};
+use crate::{
+    engine::{
+        cloze::compose::{Cloze, ClozeTemplate},
+        compose::{Engine, EngineSpec, EngineType, Shorthand},
+    },
+    query::is_identifier,
+};
+use serde::Deserialize;
+use std::path::Path;
+
+/// The placeholder of the query in the url of a bang.
+const PLACEHOLDER: &str = "{{{s}}}";
+
+#[derive(Deserialize, Debug)]
+struct Bang {
+    #[serde(rename = "s")]
+    name: String,
+    #[serde(rename = "t")]
+    trigger: String,
+    #[serde(rename = "ts", default)]
+    triggers: Vec<String>,
+    #[serde(rename = "u")]
+    url: String,
+    #[serde(rename = "c", default)]
+    category: Option<String>,
+}
+
+/// Turn the url of a bang into a template, escaping the braces it contains otherwise.
+fn template(url: &str) -> String {
+    url.split(PLACEHOLDER)
+        .map(|part| part.replace('{', "{{").replace('}', "}}"))
+        .collect::<Vec<_>>()
+        .join("{}")
+}
+
+impl Bang {
+    /// The engine of the bang, or `None` if it has no absolute url,
+    ///   counting the triggers that cannot be mentioned in `dropped`.
+    fn into_engine(self, dropped: &mut usize) -> Option<Engine> {
+        if self.trigger.is_empty() || url::Url::parse(&self.url).is_err() {
+            return None;
+        }
+
+        let mut shorthand = Vec::new();
+        for trigger in std::iter::once(&self.trigger).chain(&self.triggers) {
+            if trigger.is_empty() || shorthand.contains(trigger) {
+                continue;
+            }
+            if is_identifier(trigger) {
+                shorthand.push(trigger.clone());
+            } else {
+                *dropped += 1;
+            }
+        }
+        if shorthand.is_empty() {
+            return None;
+        }
+
+        Some(Engine {
+            id: format!("!{}", self.trigger),
+            engine: EngineSpec::Builtin(EngineType::Cloze(Cloze {
+                template: ClozeTemplate::Single(template(&self.url)),
+                args: Vec::new(),
+            })),
+            shorthand: Shorthand::Multiple(shorthand),
+            description: Some(self.name),
+            category: self.category,
+            icon: None,
+        })
+    }
+}
+
+fn collect(bangs: Vec<Bang>) -> Imported {
+    let mut imported = Imported::default();
+    let mut dropped = 0;
+    imported.extend(bangs.into_iter().filter_map(|bang| bang.into_engine(&mut dropped)));
+    if dropped > 0 {
+        log::warn!("Dropped {dropped} bang triggers that cannot be mentioned");
+    }
+    imported
+}
+
+/// Import bangs from JSON, such as DuckDuckGo's `bang.js`.
+pub fn parse_json(s: &str) -> Result<Imported, ImportError> {
+    Ok(collect(serde_json::from_str(s)?))
+}
+
+/// Import bangs from YAML, such as Kagi's bangs.
+#[cfg(feature = "yaml")]
+pub fn parse_yaml(s: &str) -> Result<Imported, ImportError> {
+    Ok(collect(serde_yaml::from_str(s)?))
+}
+
+/// Import bangs from a file, read as YAML if its extension is `.yaml` or `.yml`, and as JSON otherwise.
+/// YAML needs the `yaml` feature.
+pub fn load(path: &Path) -> Result<Imported, ImportError> {
+    let s = std::fs::read_to_string(path).map_err(|source| ImportError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        #[cfg(feature = "yaml")]
+        Some("yaml" | "yml") => parse_yaml(&s),
+        #[cfg(not(feature = "yaml"))]
+        Some("yaml" | "yml") => Err(ImportError::Invalid("YAML bangs need the `yaml` feature".to_string())),
+        _ => parse_json(&s),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_json, template};
+    use crate::{
+        compose::{Compose, EngineFactories},
+        engine::compose::Shorthand,
+    };
+
+    #[test]
+    fn test_template() {
+        assert_eq!(template("https://example.com/?q={{{s}}}"), "https://example.com/?q={}");
+        assert_eq!(
+            template("https://example.com/{x}/{{{s}}}#{{{s}}}"),
+            "https://example.com/{{x}}/{}#{}"
+        );
+        assert_eq!(template("https://example.com/"), "https://example.com/");
+    }
+
+    #[test]
+    fn test_parse() {
+        let json = r#"[
+            {"c": "Research", "d": "en.wikipedia.org", "r": 0, "s": "Wikipedia", "sc": "Reference",
+             "t": "w", "ts": ["wiki"], "u": "https://en.wikipedia.org/wiki/Special:Search?search={{{s}}}"},
+            {"s": "DuckDuckGo Images", "t": "i", "u": "/?q={{{s}}}&ia=images"}
+        ]"#;
+        let imported = parse_json(json).unwrap();
+        assert_eq!(imported.len(), 1);
+        let engine = &imported.engines[0];
+        assert_eq!(engine.id, "!w");
+        assert_eq!(engine.description.as_deref(), Some("Wikipedia"));
+        assert_eq!(engine.category.as_deref(), Some("Research"));
+        assert!(parse_json("{").is_err());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_parse_yaml() {
+        let yaml = "
+- s: GitHub
+  t: gh
+  u: https://github.com/search?q={{{s}}}
+  c: Tech
+";
+        let imported = super::parse_yaml(yaml).unwrap();
+        assert_eq!(imported.engines[0].id, "!gh");
+    }
+
+    #[test]
+    fn test_invalid_triggers() {
+        let json = r#"[
+            {"s": "9GAG", "t": "9gag", "u": "https://9gag.com/search?query={{{s}}}"},
+            {"s": "Google+", "t": "g+", "ts": ["gplus"], "u": "https://plus.google.com/s/{{{s}}}"}
+        ]"#;
+        let imported = parse_json(json).unwrap();
+        let ids: Vec<_> = imported.engines.iter().map(|engine| engine.id.as_str()).collect();
+        assert_eq!(ids, ["!g+"]);
+        assert!(matches!(&imported.engines[0].shorthand, Shorthand::Multiple(s) if s == &["gplus"]));
+    }
+
+    #[test]
+    fn test_duplicate_triggers() {
+        let json = r#"[
+            {"s": "Wikipedia", "t": "w", "u": "https://en.wikipedia.org/wiki/{{{s}}}"},
+            {"s": "Wiktionary", "t": "w", "u": "https://en.wiktionary.org/wiki/{{{s}}}"}
+        ]"#;
+        let imported = parse_json(json).unwrap();
+        let ids: Vec<_> = imported.engines.iter().map(|engine| engine.id.as_str()).collect();
+        assert_eq!(ids, ["!w", "!w_2"]);
+        assert!(matches!(&imported.engines[0].shorthand, Shorthand::Multiple(s) if s == &["w"]));
+        assert!(matches!(&imported.engines[1].shorthand, Shorthand::Multiple(s) if s.is_empty()));
+
+        let compose: Compose = toml::from_str(&toml::to_string(&imported).unwrap()).unwrap();
+        let instance = compose.build(&EngineFactories::default());
+        assert_eq!(instance.describe("!w").as_deref(), Some("Wikipedia"));
+        assert_eq!(instance.describe("!w_2").as_deref(), Some("Wiktionary"));
+        assert_eq!(instance.describe("w").as_deref(), Some("Wikipedia"));
+    }
+}
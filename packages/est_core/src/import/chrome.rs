@@ -0,0 +1,78 @@
+//! Search engines of a Chrome profile as cloze engines.
+//!
+//! The `keywords` table of the `Web Data` database has the name, the keyword and the url of every engine,
+//!   where the url is an OpenSearch template with some Google-specific parameters.
+use super::{ImportError, Imported, identifier, open_database, opensearch_template, shorthand};
+use std::path::Path;
+
+/// Import the search engines of a `Web Data` database.
+pub fn load(path: &Path) -> Result<Imported, ImportError> {
+    let connection = open_database(path)?;
+    let mut statement =
+        connection.prepare("SELECT short_name, keyword, url FROM keywords ORDER BY id")?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+
+    let mut imported = Imported::default();
+    for row in rows {
+        let (name, keyword, url) = row?;
+        if !url.contains("{searchTerms}") {
+            continue;
+        }
+        imported.push_cloze(
+            identifier(&name),
+            opensearch_template(&url),
+            shorthand(&keyword).into_iter().collect(),
+            Some(name),
+        );
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod test {
+    use super::load;
+    use crate::engine::compose::Shorthand;
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join(format!("est-web-data-{}", std::process::id()));
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE keywords (id INTEGER PRIMARY KEY, short_name TEXT, keyword TEXT, url TEXT);
+                INSERT INTO keywords VALUES
+                    (1, 'Google', 'google.com', '{google:baseURL}search?q={searchTerms}&{google:RLZ}'),
+                    (2, 'Wikipedia', 'w', 'https://en.wikipedia.org/wiki/Special:Search?search={searchTerms}'),
+                    (3, 'Wikipedia', 'wiki', 'https://en.wikipedia.org/w/index.php?search={searchTerms}'),
+                    (4, 'Start page', 'start', 'https://example.com/');",
+            )
+            .unwrap();
+        drop(connection);
+
+        let imported = load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let imported = imported.unwrap();
+        let ids: Vec<&str> = imported
+            .engines
+            .iter()
+            .map(|engine| engine.id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["google", "wikipedia", "wikipedia_2"]);
+        // `google.com` cannot be mentioned, so Google is only reachable by its id.
+        let shorthands: Vec<&[String]> = imported
+            .engines
+            .iter()
+            .map(|engine| match &engine.shorthand {
+                Shorthand::Multiple(shorthands) => shorthands.as_slice(),
+                Shorthand::Single(shorthand) => std::slice::from_ref(shorthand),
+            })
+            .collect();
+        assert_eq!(shorthands, [&[][..], &["w".to_string()], &["wiki".to_string()]]);
+    }
+}
@@ -0,0 +1,237 @@
+//! Search engines and bookmark keywords of a Firefox profile as cloze engines.
+//!
+//! - `search.json.mozlz4` lists the search engines, with their aliases as shorthands.
+//!   Engines bundled with Firefox usually have no url in it, and hidden engines are skipped.
+//! - `places.sqlite` has the bookmarks with keywords, where `%s` in the url stands for the query.
+//!   Keywords of bookmarks that post data are skipped.
+use super::{
+    ImportError, Imported, decompress, escape, identifier, open_database, opensearch::with_params,
+    opensearch_template, read, shorthand,
+};
+use serde::Deserialize;
+use std::path::Path;
+
+#[derive(Deserialize)]
+struct SearchJson {
+    #[serde(default)]
+    engines: Vec<SearchEngine>,
+}
+
+#[derive(Deserialize)]
+struct SearchEngine {
+    #[serde(rename = "_name")]
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(rename = "_urls", default)]
+    urls: Vec<SearchUrl>,
+    #[serde(rename = "_definedAliases", default)]
+    aliases: Vec<String>,
+    #[serde(rename = "_metaData", default)]
+    metadata: SearchMetadata,
+}
+
+#[derive(Deserialize, Default)]
+struct SearchMetadata {
+    #[serde(default)]
+    alias: Option<String>,
+    #[serde(default)]
+    hidden: bool,
+}
+
+#[derive(Deserialize)]
+struct SearchUrl {
+    template: String,
+    #[serde(rename = "type", default)]
+    kind: Option<String>,
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    params: Vec<SearchParam>,
+}
+
+#[derive(Deserialize)]
+struct SearchParam {
+    name: String,
+    value: String,
+}
+
+/// Import the search engines of `search.json.mozlz4`, compressed or not.
+pub fn search_engines(bytes: &[u8]) -> Result<Imported, ImportError> {
+    let search: SearchJson = serde_json::from_slice(&decompress(bytes)?)?;
+
+    let mut imported = Imported::default();
+    for engine in search
+        .engines
+        .into_iter()
+        .filter(|engine| !engine.metadata.hidden)
+    {
+        let url = engine.urls.into_iter().find(|url| {
+            url.kind.as_deref().is_none_or(|kind| kind == "text/html")
+                && url
+                    .method
+                    .as_deref()
+                    .is_none_or(|method| method.eq_ignore_ascii_case("get"))
+        });
+        let Some(url) = url else {
+            continue;
+        };
+
+        let params: Vec<(String, String)> =
+            url.params.into_iter().map(|p| (p.name, p.value)).collect();
+        let mut shorthands: Vec<String> = engine
+            .metadata
+            .alias
+            .iter()
+            .chain(&engine.aliases)
+            .filter_map(|a| shorthand(a))
+            .collect();
+        shorthands.dedup();
+        imported.push_cloze(
+            identifier(&engine.name),
+            opensearch_template(&with_params(&url.template, &params)),
+            shorthands,
+            Some(engine.description.unwrap_or(engine.name)),
+        );
+    }
+    Ok(imported)
+}
+
+/// Turn the url of a keyword bookmark into a template.
+/// `%s` is the encoded query, and `%S` the query as is.
+fn keyword_template(url: &str) -> String {
+    url.split("%s")
+        .map(|part| {
+            part.split("%S")
+                .map(escape)
+                .collect::<Vec<_>>()
+                .join("{content|raw}")
+        })
+        .collect::<Vec<_>>()
+        .join("{}")
+}
+
+/// Import the keyword bookmarks of `places.sqlite`.
+pub fn bookmark_keywords(path: &Path) -> Result<Imported, ImportError> {
+    let connection = open_database(path)?;
+    let mut statement = connection.prepare(
+        "SELECT k.keyword, p.url, \
+            (SELECT b.title FROM moz_bookmarks b WHERE b.fk = p.id AND b.title <> '' LIMIT 1) \
+        FROM moz_keywords k JOIN moz_places p ON p.id = k.place_id \
+        WHERE k.post_data IS NULL OR k.post_data = '' \
+        ORDER BY k.keyword",
+    )?;
+    let rows = statement.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?,
+        ))
+    })?;
+
+    let mut imported = Imported::default();
+    for row in rows {
+        let (keyword, url, title) = row?;
+        let Some(keyword) = shorthand(&keyword) else {
+            continue;
+        };
+        imported.push_cloze(
+            identifier(title.as_deref().unwrap_or(&keyword)),
+            keyword_template(&url),
+            vec![keyword],
+            title,
+        );
+    }
+    Ok(imported)
+}
+
+/// Import the search engines and keyword bookmarks of a profile directory, whichever it has.
+pub fn load_profile(profile: &Path) -> Result<Imported, ImportError> {
+    let mut imported = Imported::default();
+    let search = profile.join("search.json.mozlz4");
+    if search.is_file() {
+        imported.extend(search_engines(&read(&search)?)?);
+    }
+    let places = profile.join("places.sqlite");
+    if places.is_file() {
+        imported.extend(bookmark_keywords(&places)?);
+    }
+    if !search.is_file() && !places.is_file() {
+        return Err(ImportError::Invalid(format!(
+            "{} has neither search.json.mozlz4 nor places.sqlite",
+            profile.display()
+        )));
+    }
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bookmark_keywords, keyword_template, search_engines};
+    use crate::import::MOZLZ4_MAGIC;
+    use crate::engine::compose::Shorthand;
+
+    #[test]
+    fn test_search_engines() {
+        let json = br#"{"version": 6, "engines": [
+            {"_name": "Google", "_isAppProvided": true, "_metaData": {"alias": "g"}},
+            {"_name": "Rust Docs", "_definedAliases": ["@rs"], "_metaData": {"alias": "rs"},
+             "_urls": [
+                {"template": "https://example.com/suggest", "type": "application/x-suggestions+json"},
+                {"template": "https://doc.rust-lang.org/std/", "params": [{"name": "search", "value": "{searchTerms}"}]}
+             ]},
+            {"_name": "Hidden", "_metaData": {"hidden": true}, "_urls": [{"template": "https://example.com/?q={searchTerms}"}]}
+        ]}"#;
+        let mut mozlz4 = MOZLZ4_MAGIC.to_vec();
+        mozlz4.extend((json.len() as u32).to_le_bytes());
+        mozlz4.extend(lz4_flex::block::compress(json));
+
+        for bytes in [&json[..], &mozlz4] {
+            let imported = search_engines(bytes).unwrap();
+            assert_eq!(imported.len(), 1);
+            let engine = &imported.engines[0];
+            assert_eq!(engine.id, "rust_docs");
+            assert!(matches!(&engine.shorthand, Shorthand::Multiple(s) if s == &["rs"]));
+        }
+        assert!(search_engines(MOZLZ4_MAGIC).is_err());
+    }
+
+    #[test]
+    fn test_keyword_template() {
+        assert_eq!(
+            keyword_template("https://example.com/?q=%s"),
+            "https://example.com/?q={}"
+        );
+        assert_eq!(
+            keyword_template("https://example.com/%S/{x}"),
+            "https://example.com/{content|raw}/{{x}}"
+        );
+    }
+
+    #[test]
+    fn test_bookmark_keywords() {
+        let path = std::env::temp_dir().join(format!("est-places-{}.sqlite", std::process::id()));
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE moz_places (id INTEGER PRIMARY KEY, url TEXT);
+                CREATE TABLE moz_bookmarks (id INTEGER PRIMARY KEY, fk INTEGER, title TEXT);
+                CREATE TABLE moz_keywords (id INTEGER PRIMARY KEY, keyword TEXT, place_id INTEGER, post_data TEXT);
+                INSERT INTO moz_places VALUES (1, 'https://crates.io/search?q=%s'), (2, 'https://example.com/login');
+                INSERT INTO moz_bookmarks VALUES (1, 1, 'crates.io'), (2, 2, 'Login');
+                INSERT INTO moz_keywords VALUES (1, 'crates', 1, NULL), (2, 'login', 2, 'user=%s');",
+            )
+            .unwrap();
+        drop(connection);
+
+        let imported = bookmark_keywords(&path);
+        std::fs::remove_file(&path).unwrap();
+        let imported = imported.unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported.engines[0].id, "crates_io");
+        assert_eq!(
+            imported.engines[0].description.as_deref(),
+            Some("crates.io")
+        );
+    }
+}
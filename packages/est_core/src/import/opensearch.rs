@@ -0,0 +1,208 @@
+//! OpenSearch description documents as cloze engines.
+//!
+//! The engine is named after the `ShortName` and described by the `Description` of the document,
+//!   and navigates with its `Url` of type `text/html`, including the `Param`s of the url if any.
+//! Mozilla's `SearchPlugin` documents share the same elements, and are read as well.
+use super::{ImportError, Imported, identifier, opensearch_template, read};
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use std::path::Path;
+
+const TYPE_HTML: &str = "text/html";
+
+#[derive(Default)]
+struct Description {
+    short_name: String,
+    description: String,
+    url: Option<String>,
+    params: Vec<(String, String)>,
+}
+
+/// The url template of a `Url` element, if it is the one to navigate with.
+fn html_url(element: &BytesStart) -> Result<Option<String>, ImportError> {
+    let (mut kind, mut template, mut method, mut rel) = (None, None, None, None);
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(quick_xml::Error::from)?;
+        let value = attribute.unescape_value()?.into_owned();
+        match attribute.key.local_name().as_ref() {
+            b"type" => kind = Some(value),
+            b"template" => template = Some(value),
+            b"method" => method = Some(value),
+            b"rel" => rel = Some(value),
+            _ => {}
+        }
+    }
+
+    let is_html = kind.is_none_or(|kind| kind == TYPE_HTML);
+    let is_get = method.is_none_or(|method| method.eq_ignore_ascii_case("get"));
+    let is_results = rel.is_none_or(|rel| rel.split_whitespace().any(|rel| rel == "results"));
+    Ok(template.filter(|_| is_html && is_get && is_results))
+}
+
+fn param(element: &BytesStart) -> Result<Option<(String, String)>, ImportError> {
+    let (mut name, mut value) = (None, None);
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(quick_xml::Error::from)?;
+        match attribute.key.local_name().as_ref() {
+            b"name" => name = Some(attribute.unescape_value()?.into_owned()),
+            b"value" => value = Some(attribute.unescape_value()?.into_owned()),
+            _ => {}
+        }
+    }
+    Ok(name.zip(value))
+}
+
+fn parse_description(xml: &str) -> Result<Description, ImportError> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+
+    let mut description = Description::default();
+    let mut text_of: Option<Vec<u8>> = None;
+    let mut in_url = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) | Event::Empty(element)
+                if element.local_name().as_ref() == b"Url" =>
+            {
+                in_url = false;
+                if description.url.is_none()
+                    && let Some(url) = html_url(&element)?
+                {
+                    description.url = Some(url);
+                    in_url = true;
+                }
+            }
+            Event::Start(element) | Event::Empty(element)
+                if element.local_name().as_ref() == b"Param" =>
+            {
+                if in_url && let Some(param) = param(&element)? {
+                    description.params.push(param);
+                }
+            }
+            Event::Start(element) => text_of = Some(element.local_name().as_ref().to_vec()),
+            Event::Text(text) => match text_of.as_deref() {
+                Some(b"ShortName") => description.short_name = text.unescape()?.into_owned(),
+                Some(b"Description") => description.description = text.unescape()?.into_owned(),
+                _ => {}
+            },
+            Event::End(element) => {
+                if element.local_name().as_ref() == b"Url" {
+                    in_url = false;
+                }
+                text_of = None;
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(description)
+}
+
+/// Append parameters to the query string of a url.
+pub(super) fn with_params(url: &str, params: &[(String, String)]) -> String {
+    if params.is_empty() {
+        return url.to_string();
+    }
+    let query: Vec<String> = params
+        .iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect();
+    let sep = if url.contains('?') { '&' } else { '?' };
+    format!("{url}{sep}{}", query.join("&"))
+}
+
+/// Import the engine of an OpenSearch description document.
+pub fn parse(xml: &str) -> Result<Imported, ImportError> {
+    let description = parse_description(xml)?;
+    if description.short_name.is_empty() {
+        return Err(ImportError::Invalid(
+            "no `ShortName` in the description".to_string(),
+        ));
+    }
+    let url = description.url.ok_or_else(|| {
+        ImportError::Invalid(format!(
+            "no `Url` of type {TYPE_HTML} for {}",
+            description.short_name
+        ))
+    })?;
+
+    let mut imported = Imported::default();
+    let text = if description.description.is_empty() {
+        description.short_name.clone()
+    } else {
+        description.description
+    };
+    imported.push_cloze(
+        identifier(&description.short_name),
+        opensearch_template(&with_params(&url, &description.params)),
+        Vec::new(),
+        Some(text),
+    );
+    Ok(imported)
+}
+
+/// Import the engine of an OpenSearch description file.
+pub fn load(path: &Path) -> Result<Imported, ImportError> {
+    let xml = read(path)?;
+    let xml = String::from_utf8(xml).map_err(|err| ImportError::Invalid(err.to_string()))?;
+    parse(&xml)
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+    use crate::engine::{
+        cloze::compose::ClozeTemplate,
+        compose::{EngineSpec, EngineType},
+    };
+
+    fn template(imported: &super::Imported) -> &str {
+        match &imported.engines[0].engine {
+            EngineSpec::Builtin(EngineType::Cloze(cloze)) => match &cloze.template {
+                ClozeTemplate::Single(template) => template,
+                _ => panic!("Expected a single template"),
+            },
+            _ => panic!("Expected a cloze engine"),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <OpenSearchDescription xmlns="http://a9.com/-/spec/opensearch/1.1/">
+                <ShortName>Rust Docs</ShortName>
+                <Description>Search the Rust standard library</Description>
+                <Url type="application/x-suggestions+json" template="https://example.com/suggest?q={searchTerms}"/>
+                <Url type="text/html" method="get" template="https://doc.rust-lang.org/std/?search={searchTerms}&amp;ie={inputEncoding}"/>
+            </OpenSearchDescription>"#;
+        let imported = parse(xml).unwrap();
+        assert_eq!(imported.engines[0].id, "rust_docs");
+        assert_eq!(
+            imported.engines[0].description.as_deref(),
+            Some("Search the Rust standard library")
+        );
+        assert_eq!(
+            template(&imported),
+            "https://doc.rust-lang.org/std/?search={}&ie=UTF-8"
+        );
+
+        let xml = r#"<SearchPlugin xmlns="http://www.mozilla.org/2006/browser/search/">
+                <os:ShortName xmlns:os="http://a9.com/-/spec/opensearch/1.1/">Example</os:ShortName>
+                <Url type="text/html" template="https://example.com/search">
+                    <Param name="q" value="{searchTerms}"/>
+                    <Param name="src" value="est"/>
+                </Url>
+            </SearchPlugin>"#;
+        let imported = parse(xml).unwrap();
+        assert_eq!(imported.engines[0].description.as_deref(), Some("Example"));
+        assert_eq!(
+            template(&imported),
+            "https://example.com/search?q={}&src=est"
+        );
+
+        assert!(
+            parse("<OpenSearchDescription><ShortName>x</ShortName></OpenSearchDescription>")
+                .is_err()
+        );
+    }
+}
@@ -1,24 +1,46 @@
 //! Core definitions for `est`
+pub mod arguments;
 pub mod compose;
 pub mod engine;
+pub mod import;
 pub mod query;
 pub mod reaction;
+pub(crate) mod template;
 
 pub(crate) use engine::EngineNode;
-pub use query::Query;
+pub use query::{ParseOptions, Query, QueryParseError};
 pub use reaction::{AcceptanceErr, Reaction, ReactionErr, ReactionVerb};
 
 const MAX_FORWARD_DEPTH: u8 = 16;
 
 pub struct Instance {
     pub(crate) engine_registry: engine::EngineRegistry,
+    pub(crate) parse_options: ParseOptions,
+    pub(crate) resolve_options: engine::ResolveOptions,
 }
 
 impl Instance {
     pub(crate) fn engine<'i>(&'i self, id: &str) -> Result<&'i EngineNode, ReactionErr> {
-        self.engine_registry
-            .get(id)
-            .ok_or(ReactionErr::NotAccepted(AcceptanceErr::NoEngine))
+        self.engine_registry.get(id).ok_or_else(|| {
+            ReactionErr::NotAccepted(AcceptanceErr::UnknownEngine {
+                id: id.to_string(),
+                suggestions: Vec::new(),
+            })
+        })
+    }
+
+    /// Find the engine for an id written by the user, which may be auto-corrected.
+    pub(crate) fn resolve<'i>(&'i self, id: &str) -> Result<(&'i str, &'i EngineNode), ReactionErr> {
+        Ok(self.engine_registry.resolve(id, &self.resolve_options)?)
+    }
+
+    /// Parse a query with the sigils configured for the instance.
+    pub fn parse(&self, query: &str) -> Result<Query, QueryParseError> {
+        Query::parse_with(query, &self.parse_options)
+    }
+
+    pub fn parse_options(&self) -> &ParseOptions {
+        &self.parse_options
     }
 
     pub fn iter_engine_ids(&self) -> impl Iterator<Item = &String> {
@@ -29,46 +51,544 @@ impl Instance {
         self.engine_registry.description(id).map(String::from)
     }
 
+    pub fn category(&self, id: &str) -> Option<&str> {
+        self.engine_registry.category(id)
+    }
+
+    /// The url of the icon of an engine, if it has one.
+    pub fn icon(&self, id: &str) -> Option<&str> {
+        self.engine_registry.icon(id)
+    }
+
     pub async fn react(&self, mut query: Query) -> Reaction {
-        let mut engine = self.engine(query.mention_head())?;
+        let pipe = query.take_pipe();
+        let scope = query.scope.clone();
 
-        let mut count = 0u8;
-        let reaction = loop {
-            count += 1;
-            if count > MAX_FORWARD_DEPTH {
-                return Err(ReactionErr::TooManyForward);
+        let mut reaction = self.react_stage(query).await?;
+        for stage in pipe {
+            let content = reaction.into_piped_content()?;
+            let query = Query {
+                mention: stage.mention,
+                fanout: stage.fanout,
+                content,
+                scope: scope.clone(),
+                ..Default::default()
+            };
+            reaction = self.react_stage(query).await?;
+        }
+
+        Ok(reaction)
+    }
+
+    /// React to one stage of a pipeline, sending the query to every engine it mentions.
+    /// Mentions that fail are listed along with the others, unless they all fail.
+    async fn react_stage(&self, query: Query) -> Reaction {
+        if !query.is_fanout() {
+            return self.react_single(query).await;
+        }
+
+        let queries = query.split_fanout();
+        let mentions: Vec<String> = queries.iter().map(|q| q.mention_head().to_string()).collect();
+        let reactions = futures::future::join_all(queries.into_iter().map(|q| self.react_single(q))).await;
+
+        let mut navigations = Vec::with_capacity(reactions.len());
+        let mut failures = Vec::new();
+        let mut first_err = None;
+        for (mention, reaction) in mentions.into_iter().zip(reactions) {
+            let reaction = match reaction {
+                Ok(reaction) => reaction,
+                Err(err) => {
+                    failures.push((mention, err.to_string()));
+                    first_err.get_or_insert(err);
+                    continue;
+                }
+            };
+            match reaction {
+                ReactionVerb::Navigate(nav) => navigations.push(nav),
+                ReactionVerb::FanOut(fan_out) => {
+                    navigations.extend_from_slice(fan_out.navigations());
+                    failures.extend_from_slice(fan_out.failures());
+                }
+                ReactionVerb::Text(_) | ReactionVerb::Answer(_) => {
+                    return Err(ReactionErr::Incompatible(
+                        "text cannot be fanned out with other navigations".to_string(),
+                    ));
+                }
+                ReactionVerb::Forward(_) => unreachable!("Forwards are resolved by `react_single`."),
             }
+        }
 
-            engine.accept(&query, self)?;
-            let reaction = engine.react(&query, self).await?;
-            if let ReactionVerb::Forward(fwd) = reaction {
-                use reaction::Forward::*;
-                match fwd {
-                    Mention(prepend, skip) => {
-                        engine = self.engine(prepend.as_str())?;
-                        match (query.mention.len(), skip) {
-                            (0, _) => query.mention.push(prepend),
-                            (_, 0) => query.mention.insert(0, prepend),
-                            (_, 1) => query.mention[0] = prepend,
-                            (len, 2) => {
-                                query.mention[0] = prepend;
-                                if len > 1 {
-                                    query.mention.remove(1);
-                                }
-                            }
-                            _ => {
-                                query.mention = std::iter::once(prepend)
-                                    .chain(query.mention.into_iter().skip(skip))
-                                    .collect();
+        if navigations.is_empty()
+            && let Some(err) = first_err
+        {
+            return Err(err);
+        }
+        let fan_out: reaction::FanOut = navigations.into_iter().collect();
+        Ok(fan_out.with_failures(failures).into())
+    }
+
+    /// React to a query that mentions at most one engine, following forwards until a decision is made.
+    async fn react_single(&self, mut query: Query) -> Reaction {
+        let (id, engine) = self.resolve(query.mention_head())?;
+        if id != query.mention_head() {
+            query.mention[0] = id.to_string();
+        }
+
+        let mut count = 0u8;
+        self.react_engine(query, engine, &mut count).await
+    }
+
+    /// React to a query with the engine, following forwards until a decision is made.
+    /// `count` is the number of forwards so far; each fallback tried along the way counts on from it on its own.
+    fn react_engine<'i>(
+        &'i self,
+        mut query: Query,
+        mut engine: &'i EngineNode,
+        count: &'i mut u8,
+    ) -> futures::future::BoxFuture<'i, Reaction> {
+        use futures::FutureExt;
+        use reaction::Forward::*;
+
+        async move {
+            loop {
+                *count += 1;
+                if *count > MAX_FORWARD_DEPTH {
+                    return Err(ReactionErr::TooManyForward);
+                }
+
+                engine.accept(&query, self)?;
+                let (prepend, skip) = match engine.react(&query, self).await? {
+                    ReactionVerb::Forward(Mention(prepend, skip)) => (prepend, skip),
+                    ReactionVerb::Forward(Rewrite(prepend, skip, content)) => {
+                        query.content = content;
+                        (prepend, skip)
+                    }
+                    ReactionVerb::Forward(Fallback(targets, skip)) => {
+                        let mut last = ReactionErr::Nothing;
+                        for target in targets {
+                            let engine = self.engine(&target).map_err(|_| {
+                                ReactionErr::BadConfig(format!("No engine is named `{target}` to fall back to"))
+                            })?;
+                            let mut query = query.clone();
+                            prepend_mention(&mut query, target, skip);
+                            let mut count = *count;
+                            match self.react_engine(query, engine, &mut count).await {
+                                Err(err) if err.is_recoverable() => last = err,
+                                reaction => return reaction,
                             }
                         }
+                        return Err(last);
                     }
-                }
-            } else {
-                break reaction;
+                    reaction => return Ok(reaction),
+                };
+                engine = self.engine(prepend.as_str())?;
+                prepend_mention(&mut query, prepend, skip);
             }
+        }
+        .boxed()
+    }
+}
+
+/// Prepend a mention segment to the query and drop the first-n segments, see [`reaction::Forward::Mention`].
+fn prepend_mention(query: &mut Query, prepend: String, skip: usize) {
+    match (query.mention.len(), skip) {
+        (0, _) => query.mention.push(prepend),
+        (_, 0) => query.mention.insert(0, prepend),
+        (_, 1) => query.mention[0] = prepend,
+        (len, 2) => {
+            query.mention[0] = prepend;
+            if len > 1 {
+                query.mention.remove(1);
+            }
+        }
+        _ => {
+            query.mention = std::iter::once(prepend)
+                .chain(std::mem::take(&mut query.mention).into_iter().skip(skip))
+                .collect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AcceptanceErr, Instance, ReactionErr, ReactionVerb, compose::Compose};
+    use futures::executor::block_on;
+
+    const COMPOSE: &str = r#"
+        default = "google"
+
+        [[engines]]
+        id = "google"
+        type = "cloze"
+        shorthand = "g"
+        description = "Google Search"
+        icon = "https://google.com/favicon.ico"
+        template = "https://google.com/search?q={}"
+
+        [[engines]]
+        id = "bing"
+        type = "cloze"
+        template = "https://www.bing.com/search?q={}"
+    "#;
+
+    fn instance() -> Instance {
+        toml::from_str::<Compose>(COMPOSE).unwrap().into()
+    }
+
+    fn navigate(instance: &Instance, query: &str) -> Result<String, ReactionErr> {
+        match block_on(instance.react(instance.parse(query).unwrap()))? {
+            ReactionVerb::Navigate(nav) => Ok(nav.url().to_string()),
+            reaction => panic!("Expected a navigation, got {reaction:?}"),
+        }
+    }
+
+    #[test]
+    fn test_metadata() {
+        let instance = instance();
+        assert_eq!(instance.describe("g").as_deref(), Some("Google Search"));
+        assert_eq!(instance.icon("google"), Some("https://google.com/favicon.ico"));
+        assert_eq!(instance.icon("bing"), None);
+    }
+
+    #[test]
+    fn test_react_fanout() {
+        let instance = instance();
+        let reaction = block_on(instance.react("@g @bing rust".parse().unwrap())).unwrap();
+        let ReactionVerb::FanOut(fan_out) = reaction else {
+            panic!("Expected a fan-out reaction, got {reaction:?}");
         };
+        let urls: Vec<&str> = fan_out.navigations().iter().map(|nav| nav.url().as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://google.com/search?q=rust",
+                "https://www.bing.com/search?q=rust"
+            ]
+        );
 
-        Ok(reaction)
+        let reaction = block_on(instance.react("@g @nothing rust".parse().unwrap())).unwrap();
+        let ReactionVerb::FanOut(fan_out) = reaction else {
+            panic!("Expected a fan-out reaction, got {reaction:?}");
+        };
+        assert_eq!(fan_out.navigations().len(), 1);
+        assert_eq!(fan_out.failures().len(), 1);
+        assert_eq!(fan_out.failures()[0].0, "nothing");
+
+        assert!(block_on(instance.react("@nothing @typo rust".parse().unwrap())).is_err());
+    }
+
+    #[test]
+    fn test_react_pipe() {
+        let instance = instance();
+        let reaction = block_on(instance.react("@g | @bing rust".parse().unwrap())).unwrap();
+        let ReactionVerb::Navigate(nav) = reaction else {
+            panic!("Expected a navigation, got {reaction:?}");
+        };
+        assert_eq!(
+            nav.url().as_str(),
+            "https://www.bing.com/search?q=https%3A%2F%2Fgoogle.com%2Fsearch%3Fq%3Drust"
+        );
+
+        assert!(block_on(instance.react("@g @bing | @g rust".parse().unwrap())).is_err());
+    }
+
+    #[test]
+    fn test_resolve_suggestions() {
+        let instance = instance();
+        let err = navigate(&instance, "@gogle rust").unwrap_err();
+        let ReactionErr::NotAccepted(AcceptanceErr::UnknownEngine { id, suggestions }) = err else {
+            panic!("Expected an unknown engine, got {err:?}");
+        };
+        assert_eq!(id, "gogle");
+        assert_eq!(suggestions, vec!["google"]);
+
+        let err = navigate(&instance, "@bign rust").unwrap_err();
+        assert!(err.to_string().ends_with("Did you mean bing?"), "{err}");
+    }
+
+    #[test]
+    fn test_resolve_autocorrect() {
+        let compose = format!("{COMPOSE}\n[resolve]\nautocorrect = true");
+        let instance: Instance = toml::from_str::<Compose>(&compose).unwrap().into();
+        assert_eq!(
+            navigate(&instance, "@gogle rust").unwrap(),
+            "https://google.com/search?q=rust"
+        );
+        assert!(navigate(&instance, "@xyz rust").is_err());
+    }
+
+    #[test]
+    fn test_resolve_prefix() {
+        let compose = format!(
+            r#"{COMPOSE}
+            [[engines]]
+            id = "wiktionary"
+            type = "cloze"
+            shorthand = "wikti"
+            template = "https://en.wiktionary.org/w/index.php?search={{}}"
+
+            [[engines]]
+            id = "wikipedia"
+            type = "cloze"
+            template = "https://en.wikipedia.org/w/index.php?search={{}}"
+
+            [resolve]
+            prefix = true
+            "#
+        );
+        let instance: Instance = toml::from_str::<Compose>(&compose).unwrap().into();
+        assert_eq!(
+            navigate(&instance, "@wikt rust").unwrap(),
+            "https://en.wiktionary.org/w/index.php?search=rust"
+        );
+        assert_eq!(
+            navigate(&instance, "@wikip rust").unwrap(),
+            "https://en.wikipedia.org/w/index.php?search=rust"
+        );
+
+        let err = navigate(&instance, "@wik rust").unwrap_err();
+        let ReactionErr::NotAccepted(AcceptanceErr::AmbiguousEngine { id, candidates }) = err else {
+            panic!("Expected an ambiguous engine, got {err:?}");
+        };
+        assert_eq!(id, "wik");
+        assert_eq!(candidates, vec!["wikipedia", "wikti", "wiktionary"]);
+    }
+
+    #[test]
+    fn test_react_pattern() {
+        let compose = format!(
+            r#"{COMPOSE}
+            [[engines]]
+            id = "ref"
+            type = "pattern"
+            default = "google"
+            patterns = [
+                {{ regex = '^#(?<content>\d+)$', to = "issue" }},
+                {{ regex = '^(?<year>\d{{4}})/(?<month>\d{{2}})$', to = "bing", rewrite = "$month/$year" }},
+            ]
+
+            [[engines]]
+            id = "issue"
+            type = "cloze"
+            template = "https://github.com/rust-lang/rust/issues/{{}}"
+            "#
+        );
+        let instance: Instance = toml::from_str::<Compose>(&compose).unwrap().into();
+        assert_eq!(
+            navigate(&instance, "@ref #1234").unwrap(),
+            "https://github.com/rust-lang/rust/issues/1234"
+        );
+        assert_eq!(
+            navigate(&instance, "@ref 2024/05").unwrap(),
+            "https://www.bing.com/search?q=05%2F2024"
+        );
+        assert_eq!(
+            navigate(&instance, "@ref rust").unwrap(),
+            "https://google.com/search?q=rust"
+        );
+    }
+
+    #[cfg(feature = "script")]
+    #[test]
+    fn test_react_chain() {
+        let intranets = r#""intranet", "#.repeat(20);
+        let compose = format!(
+            r#"{COMPOSE}
+            [[engines]]
+            id = "lookup"
+            type = "chain"
+            targets = ["intranet", "google"]
+
+            [[engines]]
+            id = "intranet"
+            type = "script"
+            script = '''
+            if content.starts_with("wiki:") {{
+                navigate(`https://wiki.example.com/search?q=${{content.sub_string(5)}}`)
+            }} else if content == "secret" {{
+                throw "forbidden";
+            }} else {{
+                reject("not on the wiki")
+            }}
+            '''
+
+            [[engines]]
+            id = "patient"
+            type = "chain"
+            targets = [{intranets}"google"]
+
+            [[engines]]
+            id = "broken"
+            type = "chain"
+            targets = ["intranet", "nowhere"]
+
+            [[engines]]
+            id = "wikis"
+            type = "namespace"
+            children = {{ rust = "google" }}
+
+            [[engines]]
+            id = "browse"
+            type = "chain"
+            targets = ["wikis", "google"]
+
+            [[engines]]
+            id = "loop"
+            type = "chain"
+            targets = ["pool"]
+
+            [[engines]]
+            id = "pool"
+            type = "chain"
+            targets = ["loop"]
+            "#
+        );
+        let instance: Instance = toml::from_str::<Compose>(&compose).unwrap().into();
+        assert_eq!(
+            navigate(&instance, "@lookup wiki:onboarding").unwrap(),
+            "https://wiki.example.com/search?q=onboarding"
+        );
+        assert_eq!(navigate(&instance, "@lookup rust").unwrap(), "https://google.com/search?q=rust");
+
+        let err = navigate(&instance, "@lookup secret").unwrap_err();
+        assert!(
+            matches!(err, ReactionErr::NotAccepted(AcceptanceErr::Failed { .. })),
+            "{err:?}"
+        );
+        let err = navigate(&instance, "@intranet rust").unwrap_err();
+        assert!(
+            matches!(err, ReactionErr::NotAccepted(AcceptanceErr::Rejected { .. })),
+            "{err:?}"
+        );
+        assert!(matches!(navigate(&instance, "@loop rust"), Err(ReactionErr::TooManyForward)));
+
+        // Every fallback has its own budget of forwards.
+        assert_eq!(navigate(&instance, "@patient rust").unwrap(), "https://google.com/search?q=rust");
+        let err = navigate(&instance, "@broken rust").unwrap_err();
+        assert!(matches!(err, ReactionErr::BadConfig(_)), "{err:?}");
+
+        // A namespace without a default rejects the query, so the chain goes on.
+        assert_eq!(navigate(&instance, "@browse rust").unwrap(), "https://google.com/search?q=rust");
+        let err = navigate(&instance, "@wikis rust").unwrap_err();
+        assert!(
+            matches!(err, ReactionErr::NotAccepted(AcceptanceErr::Rejected { .. })),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn test_react_arguments() {
+        let compose = format!(
+            r#"{COMPOSE}
+            [[engines]]
+            id = "docs"
+            type = "cloze"
+            template = "https://docs.rs/{{arg.crate}}/{{arg.version}}/?search={{}}"
+            args = [{{ name = "crate" }}, {{ name = "version", default = "latest", rest = true }}]
+
+            [[engines]]
+            id = "gh"
+            type = "cloze"
+            template = "https://github.com/{{arg.owner}}/{{arg.repo|path}}"
+            args = [{{ name = "owner" }}, {{ name = "repo", rest = true }}]
+            "#
+        );
+        let instance: Instance = toml::from_str::<Compose>(&compose).unwrap().into();
+        assert_eq!(
+            navigate(&instance, "@docs.serde.1.0").unwrap(),
+            "https://docs.rs/serde/1.0/?search="
+        );
+        assert_eq!(
+            navigate(&instance, "@docs.tokio spawn").unwrap(),
+            "https://docs.rs/tokio/latest/?search=spawn"
+        );
+        assert_eq!(
+            navigate(&instance, "@gh.rust-lang.rust").unwrap(),
+            "https://github.com/rust-lang/rust"
+        );
+
+        for query in ["@docs rust", "@gh.rust-lang"] {
+            let err = navigate(&instance, query).unwrap_err();
+            assert!(
+                matches!(err, ReactionErr::NotAccepted(AcceptanceErr::BadArguments { .. })),
+                "{query}: {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_custom_engine() {
+        use crate::{Query, Reaction, compose::EngineFactories, engine::{Engine, EngineNode}, reaction::Text};
+
+        struct Echo {
+            identifier: String,
+            prefix: String,
+        }
+
+        impl Engine for Echo {
+            fn identifier(&self) -> &str {
+                &self.identifier
+            }
+
+            fn react<'e, 'q: 'e, 'i: 'e>(
+                &'e self,
+                query: &'q Query,
+                _instance: &'i Instance,
+            ) -> impl Future<Output = Reaction> + Send + 'e {
+                let text = format!("{}{}", self.prefix, query.content());
+
+                async move { Ok(Text::new(text).into()) }
+            }
+        }
+
+        let mut factories = EngineFactories::default();
+        factories.register("echo", |identifier, config| {
+            let prefix = config["prefix"].as_str().ok_or("`prefix` is required")?.to_string();
+            Ok(EngineNode::custom(Echo { identifier, prefix }))
+        });
+
+        let compose = format!(
+            r#"{COMPOSE}
+            [[engines]]
+            id = "echo"
+            type = "echo"
+            prefix = "> "
+            "#
+        );
+        let instance = toml::from_str::<Compose>(&compose).unwrap().build(&factories);
+        let reaction = block_on(instance.react(instance.parse("@echo rust").unwrap())).unwrap();
+        let ReactionVerb::Text(text) = reaction else {
+            panic!("Expected a text, got {reaction:?}");
+        };
+        assert_eq!(text.text(), "> rust");
+
+        let bad = format!("{COMPOSE}\n[[engines]]\nid = \"bad\"\ntype = \"cloze\"\ntemplate = 1");
+        assert!(toml::from_str::<Compose>(&bad).is_err());
+    }
+
+    #[test]
+    fn test_import_bangs() {
+        let path = std::env::temp_dir().join(format!("est-bangs-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"[
+                {"s": "Wikipedia", "t": "w", "ts": ["wiki"], "c": "Research", "u": "https://en.wikipedia.org/wiki/{{{s}}}"},
+                {"s": "Google", "t": "g", "u": "https://www.google.com/search?q={{{s}}}"},
+                {"s": "Bing", "t": "bing", "ts": ["b"], "u": "https://www.bing.com/?q={{{s}}}"}
+            ]"#,
+        )
+        .unwrap();
+        let compose = format!("{COMPOSE}\n[import]\nbangs = {:?}", path.display().to_string());
+        let instance: Instance = toml::from_str::<Compose>(&compose).unwrap().into();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(navigate(&instance, "@wiki Rust").unwrap(), "https://en.wikipedia.org/wiki/Rust");
+        assert_eq!(instance.category("w"), Some("Research"));
+        assert_eq!(instance.describe("!w").as_deref(), Some("Wikipedia"));
+        // Engines of the compose file win.
+        assert_eq!(navigate(&instance, "@g rust").unwrap(), "https://google.com/search?q=rust");
+        assert_eq!(navigate(&instance, "@bing rust").unwrap(), "https://www.bing.com/search?q=rust");
+        assert_eq!(navigate(&instance, "@b rust").unwrap(), "https://www.bing.com/?q=rust");
+        assert!(instance.describe("!g").is_none());
     }
 }
@@ -1,17 +1,137 @@
-use std::{default::Default, str::FromStr};
+use std::{default::Default, ops::Range, str::FromStr};
 
+use indexmap::IndexMap;
 use smallvec::SmallVec;
+use thiserror::Error;
 
+mod display;
 mod parse;
 
-#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub use display::DisplayQuery;
+pub use parse::ParseOptions;
+pub(crate) use parse::is_identifier;
+
+/// The segments of a single mention, e.g. `["docs", "serde"]` for `@docs.serde`.
+pub type Mention = SmallVec<[String; 1]>;
+
+#[derive(Clone, Debug, Default)]
 pub struct Query {
-    pub mention: SmallVec<[String; 1]>,
+    pub mention: Mention,
+    /// Further mentions that the query should be sent to along with `mention`,
+    ///   as in `@g @bing rust`.
+    pub fanout: Vec<Mention>,
+    /// Later stages of a pipeline like `@translate.en | @g 你好`.
+    /// The reaction of each stage becomes the content of the next one.
+    pub pipe: Vec<Stage>,
     pub content: String,
-    pub scope: Option<String>,
+    /// Scopes of the query, keyed by their names, in the order they are written.
+    /// An unkeyed scope like `!week` is stored under [`Query::SCOPE_UNKEYED`].
+    /// A key is given at most once, as in `!lang=zh !week` but not `!old !new`.
+    pub scope: IndexMap<String, String>,
+    /// Where each segment of the query appears in the source string.
+    /// They describe the source as written and are not updated when the query is rewritten,
+    ///   nor do they take part in comparing queries.
+    pub spans: Vec<SegmentSpan>,
+}
+
+impl PartialEq for Query {
+    fn eq(&self, other: &Self) -> bool {
+        self.mention == other.mention
+            && self.fanout == other.fanout
+            && self.pipe == other.pipe
+            && self.content == other.content
+            && self.scope == other.scope
+    }
+}
+
+impl Eq for Query {}
+
+/// The engines mentioned by one stage of a pipeline.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stage {
+    pub mention: Mention,
+    pub fanout: Vec<Mention>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SegmentKind {
+    Mention,
+    Scope,
+    Content,
+    Pipe,
+}
+
+/// The byte range of a segment in the source string of a query, sigils included.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SegmentSpan {
+    pub kind: SegmentKind,
+    pub range: Range<usize>,
+}
+
+/// An error raised when a query cannot be parsed.
+#[derive(Error, Clone, Debug, PartialEq, Eq)]
+#[error("{message} at byte {}", span.start)]
+pub struct QueryParseError {
+    span: Range<usize>,
+    expected: Option<String>,
+    message: String,
+}
+
+impl QueryParseError {
+    /// The byte range in the source string where parsing failed.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// The token expected at the failing position, if known.
+    pub fn expected(&self) -> Option<&str> {
+        self.expected.as_deref()
+    }
+
+    /// A human-readable description of the failure.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl From<winnow::error::ParseError<&str, winnow::error::ContextError>> for QueryParseError {
+    fn from(err: winnow::error::ParseError<&str, winnow::error::ContextError>) -> Self {
+        use winnow::error::StrContext;
+
+        let offset = err.offset();
+        let width = err.input()[offset..].chars().next().map_or(0, char::len_utf8);
+
+        let mut label = None;
+        let mut expected = None;
+        for context in err.inner().context() {
+            match context {
+                StrContext::Label(l) => { let _ = label.get_or_insert(*l); }
+                StrContext::Expected(e) => { let _ = expected.get_or_insert(e.to_string()); }
+                _ => {}
+            }
+        }
+
+        let mut message = match label {
+            Some(label) if width == 0 => format!("unterminated {label}"),
+            Some(label) => format!("invalid {label}"),
+            None => "invalid query".to_string(),
+        };
+        if let Some(expected) = &expected {
+            message = format!("{message}, expected {expected}");
+        }
+
+        Self {
+            span: offset..offset + width,
+            expected,
+            message,
+        }
+    }
 }
 
 impl Query {
+    /// The key under which an unkeyed scope is stored.
+    pub const SCOPE_UNKEYED: &'static str = "";
+
     #[inline]
     pub fn content(&self) -> &str {
         &self.content
@@ -27,22 +147,94 @@ impl Query {
         self.mention.get(1..).unwrap_or(&[])
     }
 
+    /// Get the value of the scope with the given key.
+    #[inline]
+    pub fn scope(&self, key: &str) -> Option<&str> {
+        self.scope.get(key).map(String::as_str)
+    }
+
+    /// Get the value of the unkeyed scope.
+    #[inline]
+    pub fn scope_unkeyed(&self) -> Option<&str> {
+        self.scope(Self::SCOPE_UNKEYED)
+    }
+
+    /// Whether the query mentions more than one engine.
+    #[inline]
+    pub fn is_fanout(&self) -> bool {
+        !self.fanout.is_empty()
+    }
+
+    /// Parse a query with the given sigils.
+    pub fn parse_with(s: &str, options: &ParseOptions) -> Result<Self, QueryParseError> {
+        use winnow::Parser;
+        (|input: &mut &str| parse::parse_query(input, options))
+            .parse(s)
+            .map_err(QueryParseError::from)
+    }
+
+    /// Write the query in its canonical form with the given sigils.
+    pub fn display_with<'q>(&'q self, options: &'q ParseOptions) -> DisplayQuery<'q> {
+        DisplayQuery {
+            query: self,
+            options,
+        }
+    }
+
+    /// Take the later stages of the pipeline out of the query, leaving only the first stage.
+    #[inline]
+    pub fn take_pipe(&mut self) -> Vec<Stage> {
+        std::mem::take(&mut self.pipe)
+    }
+
+    /// Split a fan-out query into one query per mention, in the order they are written.
+    pub fn split_fanout(self) -> Vec<Query> {
+        let Query { mention, fanout, pipe, content, scope, spans } = self;
+        std::iter::once(mention)
+            .chain(fanout)
+            .map(|mention| Query {
+                mention,
+                fanout: Vec::new(),
+                pipe: pipe.clone(),
+                content: content.clone(),
+                scope: scope.clone(),
+                spans: spans.clone(),
+            })
+            .collect()
+    }
+
     #[inline]
-    pub fn with_mention(&self, mention: SmallVec<[String; 1]>) -> Self {
+    pub fn with_mention(&self, mention: Mention) -> Self {
         Self {
             mention,
+            fanout: self.fanout.clone(),
+            pipe: self.pipe.clone(),
             content: self.content.clone(),
             scope: self.scope.clone(),
+            spans: self.spans.clone(),
         }
     }
 }
 
 impl FromStr for Query {
-    type Err = winnow::error::ContextError;
+    type Err = QueryParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        use winnow::{error::ParseError, Parser};
-        parse::parse_query.parse(s).map_err(ParseError::into_inner)
+        Self::parse_with(s, &ParseOptions::default())
+    }
+}
+
+/// Queries are serialized as their canonical string form.
+impl serde::Serialize for Query {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Query {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
     }
 }
 
@@ -56,9 +248,38 @@ mod test {
         let reference = Query {
             mention: vec!["mention".to_string()].into(),
             content: "".to_string(),
-            scope: None,
+            ..Default::default()
         };
         let target = input.parse();
         assert_eq!(target, Ok(reference));
     }
+
+    #[test]
+    fn test_parse_query_error() {
+        let err = r#"@g "rust"#.parse::<Query>().unwrap_err();
+        assert_eq!(err.span(), 8..8);
+        assert_eq!(err.expected(), Some("`\"`"));
+        assert_eq!(err.message(), "unterminated quoted content, expected `\"`");
+
+        let err = "@g !old rust !new".parse::<Query>().unwrap_err();
+        assert_eq!(err.span(), 13..14);
+        assert_eq!(err.message(), "invalid scope, expected a key not given before");
+        let err = "@g !lang=en rust !lang=zh".parse::<Query>().unwrap_err();
+        assert_eq!(err.span(), 17..18);
+    }
+
+    #[test]
+    fn test_query_spans() {
+        use super::{SegmentKind, SegmentSpan};
+        let query: Query = "@g.h rust ！lang=zh \"a b\"".parse().unwrap();
+        assert_eq!(
+            query.spans,
+            vec![
+                SegmentSpan { kind: SegmentKind::Mention, range: 0..4 },
+                SegmentSpan { kind: SegmentKind::Content, range: 5..9 },
+                SegmentSpan { kind: SegmentKind::Scope, range: 10..20 },
+                SegmentSpan { kind: SegmentKind::Content, range: 21..26 },
+            ]
+        );
+    }
 }
@@ -0,0 +1,178 @@
+//! Canonical form of a query.
+//!
+//! Sigils are always written in ASCII, mentions come first, then scopes, then later stages
+//!   of the pipeline, and the content comes last, escaped or quoted where it would otherwise
+//!   be read as something else.
+//! Any query produced by the parser survives a round trip through its canonical form.
+
+use super::{
+    parse::{ParseOptions, ESCAPABLE_QUOTED, SIGIL_ESCAPE, SIGIL_QUOTE, UNICODE_ID_BLANK},
+    Mention, Query,
+};
+use std::{
+    fmt::{self, Display, Formatter, Write},
+    sync::LazyLock,
+};
+
+static DEFAULT_OPTIONS: LazyLock<ParseOptions> = LazyLock::new(ParseOptions::default);
+
+/// A query written in its canonical form with the given sigils, see [`Query::display_with`].
+pub struct DisplayQuery<'q> {
+    pub(super) query: &'q Query,
+    pub(super) options: &'q ParseOptions,
+}
+
+/// The canonical sigil of a set.
+fn canonical(set: &[char]) -> char {
+    set[0]
+}
+
+impl DisplayQuery<'_> {
+    fn mention(&self, mention: &Mention) -> String {
+        let sep = canonical(&self.options.path_sep).to_string();
+        format!("{}{}", canonical(&self.options.mention), mention.join(&sep))
+    }
+
+    fn write_content(&self, f: &mut Formatter<'_>, content: &str) -> fmt::Result {
+        if content.trim() != content {
+            return write_quoted(f, content);
+        }
+
+        let mut at_word_start = true;
+        for c in content.chars() {
+            let is_blank = UNICODE_ID_BLANK.contains(c);
+            if at_word_start && !is_blank && self.options.is_escapable(c) {
+                f.write_char(SIGIL_ESCAPE)?;
+            }
+            f.write_char(c)?;
+            at_word_start = is_blank;
+        }
+        Ok(())
+    }
+}
+
+fn write_quoted(f: &mut Formatter<'_>, content: &str) -> fmt::Result {
+    f.write_char(SIGIL_QUOTE)?;
+    for c in content.chars() {
+        if ESCAPABLE_QUOTED.contains(&c) {
+            f.write_char(SIGIL_ESCAPE)?;
+        }
+        f.write_char(c)?;
+    }
+    f.write_char(SIGIL_QUOTE)
+}
+
+impl Display for DisplayQuery<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let DisplayQuery { query, options } = self;
+        let mut pieces: Vec<String> = Vec::new();
+
+        if !query.mention.is_empty() {
+            pieces.push(self.mention(&query.mention));
+        }
+        pieces.extend(query.fanout.iter().map(|m| self.mention(m)));
+
+        let sigil_scope = canonical(&options.scope);
+        let sep_scope_kv = canonical(&options.scope_kv_sep);
+        for (key, value) in &query.scope {
+            if key.is_empty() && !value.contains(options.scope_kv_sep.as_slice()) {
+                pieces.push(format!("{sigil_scope}{value}"));
+            } else {
+                pieces.push(format!("{sigil_scope}{key}{sep_scope_kv}{value}"));
+            }
+        }
+
+        for stage in &query.pipe {
+            pieces.push(canonical(&options.pipe).to_string());
+            if !stage.mention.is_empty() {
+                pieces.push(self.mention(&stage.mention));
+            }
+            pieces.extend(stage.fanout.iter().map(|m| self.mention(m)));
+        }
+
+        f.write_str(&pieces.join(" "))?;
+
+        if !query.content.is_empty() {
+            if !pieces.is_empty() {
+                f.write_char(' ')?;
+            }
+            self.write_content(f, &query.content)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for Query {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.display_with(&DEFAULT_OPTIONS).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Query;
+
+    fn round_trip(input: &str) -> String {
+        let query: Query = input.parse().unwrap();
+        let display = query.to_string();
+        let reparsed: Query = display.parse().unwrap();
+        assert_eq!(reparsed, query, "input: {input}, display: {display}");
+        display
+    }
+
+    #[test]
+    fn test_display_canonical() {
+        for (input, canonical) in [
+            ("@mention.hello content hi there ", "@mention.hello content hi there"),
+            ("rust ＠g。h ！lang＝zh", "@g.h !lang=zh rust"),
+            ("a !week @g b", "@g !week a b"),
+            ("@translate.en | @g 你好 ｜ @qr", "@translate.en | @g | @qr 你好"),
+            ("@g @bing rust", "@g @bing rust"),
+            ("| @g x", "| @g x"),
+            ("!=a=b", "!=a=b"),
+            ("", ""),
+        ] {
+            assert_eq!(round_trip(input), canonical, "input: {input}");
+        }
+    }
+
+    #[test]
+    fn test_display_escape() {
+        for (input, canonical) in [
+            (r"@g \@rustlang", r"@g \@rustlang"),
+            (r#"@g "!bang @at" end"#, r#"@g \!bang \@at end"#),
+            (r#""  padded  ""#, r#""  padded  ""#),
+            (r#"" \"quoted\" \\ ""#, r#"" \"quoted\" \\ ""#),
+            (r"C:\Users \\ \|", r"C:\Users \\ \|"),
+            (r#"\"open"#, r#"\"open"#),
+            ("user@example.com a|b", "user@example.com a|b"),
+            ("\\＠fullwidth \\！", "\\＠fullwidth \\！"),
+        ] {
+            assert_eq!(round_trip(input), canonical, "input: {input}");
+        }
+    }
+
+    #[test]
+    fn test_display_unicode_identifiers() {
+        for ident in ["你好", "日本語", "テスト", "Ελληνικά", "русский_1", "한국어", "ñandú", "x٣"] {
+            let input = format!("＠{ident}。{ident} ！{ident}＝{ident} {ident}");
+            let display = round_trip(&input);
+            assert_eq!(display, format!("@{ident}.{ident} !{ident}={ident} {ident}"));
+        }
+    }
+
+    #[test]
+    fn test_display_custom_sigils() {
+        use crate::query::ParseOptions;
+        let options = ParseOptions {
+            mention: vec![':', '/'],
+            scope: vec!['#'],
+            ..Default::default()
+        };
+        let query = Query::parse_with(r"/docs.serde #lang=en @user \#tag", &options).unwrap();
+        let display = query.display_with(&options).to_string();
+        assert_eq!(display, r":docs.serde #lang=en @user \#tag");
+        assert_eq!(Query::parse_with(&display, &options), Ok(query));
+    }
+}
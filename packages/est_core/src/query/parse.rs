@@ -1,15 +1,23 @@
-use super::Query;
+use super::{Mention, Query, SegmentKind, SegmentSpan, Stage};
+
+use std::{borrow::Cow, collections::HashSet, ops::Range};
 
 use icu_properties::sets::{blank, id_continue, id_start, CodePointSetDataBorrowed};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
 use winnow::{
-    combinator::{alt, dispatch, fail, repeat, separated},
+    combinator::{
+        alt, cut_err, empty, eof, fail, opt, peek, preceded, repeat, terminated,
+    },
+    error::{StrContext, StrContextValue},
     prelude::*,
-    token::{any, one_of, take_while},
+    stream::Offset,
+    token::{any, one_of, take_till, take_while},
 };
 
 static UNICODE_ID_START: CodePointSetDataBorrowed<'_> = id_start();
 static UNICODE_ID_CONTINUE: CodePointSetDataBorrowed<'_> = id_continue();
-static UNICODE_ID_BLANK: CodePointSetDataBorrowed<'_> = blank();
+pub(super) static UNICODE_ID_BLANK: CodePointSetDataBorrowed<'_> = blank();
 
 macro_rules! contains {
     (not $set:expr) => {
@@ -20,20 +28,118 @@ macro_rules! contains {
     };
 }
 
-const SIGIL_MENTION: char = '@';
-const SIGIL_MENTION_ALT: char = '\u{FF20}';
+const SIGIL_MENTION: [char; 2] = ['@', '\u{FF20}'];
+const SIGIL_SCOPE: [char; 2] = ['!', '\u{FF01}'];
+const SIGIL_PATH_SEP: [char; 2] = ['.', '\u{3002}'];
+const SIGIL_SCOPE_KV_SEP: [char; 2] = ['=', '\u{FF1D}'];
+const SIGIL_PIPE: [char; 2] = ['|', '\u{FF5C}'];
+
+pub(super) const SIGIL_QUOTE: char = '"';
+pub(super) const SIGIL_ESCAPE: char = '\\';
+
+/// Characters that can be escaped inside a quoted span.
+pub(super) const ESCAPABLE_QUOTED: [char; 2] = [SIGIL_QUOTE, SIGIL_ESCAPE];
+
+/// Sigils recognized by the parser.
+///
+/// Each sigil accepts a set of alternatives, and the first one is used when a query is written
+///   in its canonical form.
+/// Quotes `"` and the escape `\\` are not configurable.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParseOptions {
+    /// Sigils that start a mention, `@` and `＠` by default.
+    pub mention: Vec<char>,
+    /// Sigils that start a scope, `!` and `！` by default.
+    pub scope: Vec<char>,
+    /// Separators between the segments of a mention, `.` and `。` by default.
+    pub path_sep: Vec<char>,
+    /// Separators between the key and the value of a scope, `=` and `＝` by default.
+    pub scope_kv_sep: Vec<char>,
+    /// Sigils that separate the stages of a pipeline, `|` and `｜` by default.
+    pub pipe: Vec<char>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            mention: SIGIL_MENTION.into(),
+            scope: SIGIL_SCOPE.into(),
+            path_sep: SIGIL_PATH_SEP.into(),
+            scope_kv_sep: SIGIL_SCOPE_KV_SEP.into(),
+            pipe: SIGIL_PIPE.into(),
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Check that every sigil set is non-empty and has no char of identifiers,
+    ///   and that the sigils starting a word are unambiguous, also with the separators.
+    pub fn validate(&self) -> Result<(), String> {
+        let sets = [
+            ("mention", &self.mention),
+            ("scope", &self.scope),
+            ("path_sep", &self.path_sep),
+            ("scope_kv_sep", &self.scope_kv_sep),
+            ("pipe", &self.pipe),
+        ];
+        for (name, set) in sets {
+            if set.is_empty() {
+                return Err(format!("No sigil is given for `{name}`."));
+            }
+            // Chars of identifiers and mention arguments would be taken as part of them.
+            if let Some(c) = set.iter().find(|c| {
+                ESCAPABLE_QUOTED.contains(c)
+                    || UNICODE_ID_BLANK.contains(**c)
+                    || UNICODE_ID_CONTINUE.contains(**c)
+                    || **c == '-'
+            }) {
+                return Err(format!("`{c}` cannot be used as a sigil for `{name}`."));
+            }
+        }
+
+        let leading = [("mention", &self.mention), ("scope", &self.scope), ("pipe", &self.pipe)];
+        for (i, (name, set)) in leading.iter().enumerate() {
+            for (other, other_set) in &leading[i + 1..] {
+                if let Some(c) = set.iter().find(|c| other_set.contains(c)) {
+                    return Err(format!("`{c}` is a sigil for both `{name}` and `{other}`."));
+                }
+            }
+        }
+        let separators = [("path_sep", &self.path_sep), ("scope_kv_sep", &self.scope_kv_sep)];
+        for (name, set) in separators {
+            for (other, other_set) in &leading {
+                if let Some(c) = set.iter().find(|c| other_set.contains(c)) {
+                    return Err(format!("`{c}` is a sigil for both `{name}` and `{other}`."));
+                }
+            }
+        }
 
-const SIGIL_SCOPE: char = '!';
-const SIGIL_SCOPE_ALT: char = '\u{FF01}';
+        Ok(())
+    }
 
-const SIGIL_PATH_SEP: (char, char) = ('.', '\u{3002}');
+    /// Whether a character loses its special meaning at the start of a word when preceded by [`SIGIL_ESCAPE`].
+    pub(super) fn is_escapable(&self, c: char) -> bool {
+        self.mention.contains(&c)
+            || self.scope.contains(&c)
+            || self.pipe.contains(&c)
+            || ESCAPABLE_QUOTED.contains(&c)
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Segment<'i> {
     Whitespace(&'i str),
     Mention(Vec<&'i str>),
-    Scope(&'i str),
+    /// A scope as a key-value pair, the key is empty if the scope is unkeyed.
+    Scope(&'i str, &'i str),
     Content(&'i str),
+    /// A `"..."` span, kept verbatim in the content with its escapes resolved.
+    Quoted(Cow<'i, str>),
+    /// A single escaped character, e.g. `\@`.
+    Escaped(char),
+    /// A standalone `|` separating two stages of a pipeline.
+    Pipe,
 }
 
 fn parse_identifier<'i>(input: &mut &'i str) -> ModalResult<&'i str> {
@@ -45,15 +151,42 @@ fn parse_identifier<'i>(input: &mut &'i str) -> ModalResult<&'i str> {
         .parse_next(input)
 }
 
-fn parse_mention<'i>(input: &mut &'i str) -> ModalResult<Segment<'i>> {
-    separated(1.., parse_identifier, one_of(SIGIL_PATH_SEP))
-    .map(Segment::Mention)
-    .parse_next(input)
+/// Whether the text can be mentioned as an engine, like `wiki` but unlike `google.com` or `9gag`.
+pub(crate) fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| UNICODE_ID_START.contains(c)) && chars.all(|c| UNICODE_ID_CONTINUE.contains(c))
+}
+
+/// Parse a segment of a mention after the first one.
+/// Unlike the first segment, it may start with a digit and contain `-`,
+///   so that it can carry arguments as in `@docs.serde.1.0` or `@gh.rust-lang.rust`.
+fn parse_mention_argument<'i>(input: &mut &'i str) -> ModalResult<&'i str> {
+    take_while(1.., |c: char| c == '-' || UNICODE_ID_CONTINUE.contains(c)).parse_next(input)
 }
 
-fn parse_scope<'i>(input: &mut &'i str) -> ModalResult<Segment<'i>> {
-    take_while(0.., contains!(not UNICODE_ID_BLANK))
-        .map(Segment::Scope)
+fn parse_mention<'i>(input: &mut &'i str, options: &ParseOptions) -> ModalResult<Segment<'i>> {
+    (
+        parse_identifier,
+        repeat(
+            0..,
+            preceded(one_of(|c| options.path_sep.contains(&c)), parse_mention_argument),
+        ),
+    )
+        .map(|(head, tail): (&str, Vec<&str>)| {
+            Segment::Mention(std::iter::once(head).chain(tail).collect())
+        })
+        .parse_next(input)
+}
+
+fn parse_scope<'i>(input: &mut &'i str, options: &ParseOptions) -> ModalResult<Segment<'i>> {
+    (
+        opt(terminated(
+            alt((parse_identifier, empty.value(""))),
+            one_of(|c| options.scope_kv_sep.contains(&c)),
+        )),
+        take_while(0.., contains!(not UNICODE_ID_BLANK)),
+    )
+        .map(|(key, value)| Segment::Scope(key.unwrap_or(Query::SCOPE_UNKEYED), value))
         .parse_next(input)
 }
 
@@ -69,61 +202,195 @@ fn parse_content<'i>(input: &mut &'i str) -> ModalResult<Segment<'i>> {
         .parse_next(input)
 }
 
-fn parse_sigil<'i>(input: &mut &'i str) -> ModalResult<Segment<'i>> {
-    dispatch! { any;
-        SIGIL_MENTION | SIGIL_MENTION_ALT => parse_mention,
-        SIGIL_SCOPE | SIGIL_SCOPE_ALT => parse_scope,
-        _ => fail,
-    }
+fn parse_quoted<'i>(input: &mut &'i str) -> ModalResult<Segment<'i>> {
+    terminated(
+        repeat(
+            0..,
+            alt((
+                take_till(1.., ESCAPABLE_QUOTED).map(Cow::Borrowed),
+                preceded(SIGIL_ESCAPE, one_of(ESCAPABLE_QUOTED))
+                    .map(|c: char| Cow::Owned(c.to_string())),
+                SIGIL_ESCAPE.take().map(Cow::Borrowed),
+            )),
+        )
+        .fold(
+            || Cow::Borrowed(""),
+            |mut quoted: Cow<'i, str>, fragment| {
+                if quoted.is_empty() {
+                    fragment
+                } else {
+                    quoted.to_mut().push_str(&fragment);
+                    quoted
+                }
+            },
+        ),
+        cut_err(SIGIL_QUOTE)
+            .context(StrContext::Label("quoted content"))
+            .context(StrContext::Expected(StrContextValue::CharLiteral(SIGIL_QUOTE))),
+    )
+    .map(Segment::Quoted)
     .parse_next(input)
 }
 
+fn parse_escaped<'i>(input: &mut &'i str, options: &ParseOptions) -> ModalResult<Segment<'i>> {
+    one_of(|c| options.is_escapable(c)).map(Segment::Escaped).parse_next(input)
+}
+
+fn parse_pipe<'i>(input: &mut &'i str) -> ModalResult<Segment<'i>> {
+    peek(alt((eof.void(), one_of(contains!(UNICODE_ID_BLANK)).void())))
+        .value(Segment::Pipe)
+        .parse_next(input)
+}
 
-pub fn parse_query(input: &mut &str) -> ModalResult<Query> {
-    let segments: Vec<Segment> = repeat(0.., alt((
-        parse_sigil,
-        parse_whitespace,
-        parse_content,
-    )))
+fn parse_sigil<'i>(input: &mut &'i str, options: &ParseOptions) -> ModalResult<Segment<'i>> {
+    let sigil = any.parse_next(input)?;
+    if options.mention.contains(&sigil) {
+        parse_mention(input, options)
+    } else if options.scope.contains(&sigil) {
+        parse_scope(input, options)
+    } else if options.pipe.contains(&sigil) {
+        parse_pipe(input)
+    } else if sigil == SIGIL_QUOTE {
+        parse_quoted(input)
+    } else if sigil == SIGIL_ESCAPE {
+        parse_escaped(input, options)
+    } else {
+        fail.parse_next(input)
+    }
+}
+
+pub fn parse_query<'i>(input: &mut &'i str, options: &ParseOptions) -> ModalResult<Query> {
+    let origin = *input;
+    let mut keys: HashSet<&str> = HashSet::new();
+    let segments: Vec<(Segment, Range<usize>)> = repeat(0.., |input: &mut &'i str| {
+        let before = *input;
+        let start = input.offset_from(&origin);
+        let segment = alt((
+            |input: &mut &'i str| parse_sigil(input, options),
+            parse_whitespace,
+            parse_content,
+        ))
+        .parse_next(input)?;
+        // A scope given twice would silently replace the first one.
+        if let Segment::Scope(key, _) = segment
+            && !keys.insert(key)
+        {
+            *input = before;
+            return cut_err(fail)
+                .context(StrContext::Label("scope"))
+                .context(StrContext::Expected(StrContextValue::Description("a key not given before")))
+                .parse_next(input);
+        }
+        Ok((segment, start..input.offset_from(&origin)))
+    })
         .parse_next(input)?;
 
-    let mut mention: Option<Vec<&str>> = None;
-    let mut scope: Option<&str> = None;
+    // Mentions of each stage of the pipeline.
+    let mut stages: Vec<Vec<Vec<&str>>> = vec![Vec::new()];
+    let mut scope: IndexMap<String, String> = IndexMap::new();
     let mut content: String = String::new();
     let mut in_content = false;
+    // Whitespace is only kept when it separates two pieces of content,
+    //   so that the content never starts or ends with unquoted blanks.
+    let mut pending_whitespace: Option<&str> = None;
+    let mut ends_quoted = false;
+    let mut spans: Vec<SegmentSpan> = Vec::new();
+
+    for (segment, range) in segments {
+        let kind = match segment {
+            Segment::Whitespace(..) => None,
+            Segment::Mention(..) => Some(SegmentKind::Mention),
+            Segment::Scope(..) => Some(SegmentKind::Scope),
+            Segment::Pipe => Some(SegmentKind::Pipe),
+            Segment::Content(..) | Segment::Quoted(..) | Segment::Escaped(..) => {
+                Some(SegmentKind::Content)
+            }
+        };
+        match (kind, spans.last_mut()) {
+            (Some(SegmentKind::Content), Some(last))
+                if last.kind == SegmentKind::Content && last.range.end == range.start =>
+            {
+                last.range.end = range.end;
+            }
+            (Some(kind), _) => spans.push(SegmentSpan { kind, range }),
+            (None, _) => {}
+        }
 
-    for segment in segments {
         if !matches!(segment, Segment::Whitespace(..)) {
-            in_content = matches!(segment, Segment::Content(..));
+            in_content = matches!(
+                segment,
+                Segment::Content(..) | Segment::Quoted(..) | Segment::Escaped(..)
+            );
+        }
+
+        if in_content
+            && !matches!(segment, Segment::Whitespace(..))
+            && let Some(w) = pending_whitespace.take()
+            && !content.is_empty()
+        {
+            content.push_str(w);
         }
 
         match segment {
-            Segment::Mention(m) => { let _ = mention.insert(m); }
-            Segment::Whitespace(w) => { if in_content { content.push_str(w); } }
-            Segment::Content(c) => { content.push_str(c); }
-            Segment::Scope(s) => { let _ = scope.insert(s); }
+            Segment::Mention(m) => { stages.last_mut().unwrap().push(m); }
+            Segment::Pipe => { stages.push(Vec::new()); }
+            Segment::Whitespace(w) => { if in_content { pending_whitespace = Some(w); } }
+            Segment::Content(c) => {
+                if content.is_empty() {
+                    content.push_str(c.trim_start());
+                } else {
+                    content.push_str(c);
+                }
+                ends_quoted = false;
+            }
+            Segment::Quoted(q) => {
+                content.push_str(&q);
+                ends_quoted = true;
+            }
+            Segment::Escaped(c) => {
+                content.push(c);
+                ends_quoted = false;
+            }
+            Segment::Scope(k, v) => { scope.insert(k.to_string(), v.to_string()); }
         }
     }
 
-    let mention = mention.map(|v| v.into_iter().map(String::from).collect()).unwrap_or_default();
-    let scope = scope.map(String::from);
+    let mut stages = stages.into_iter().map(|mentions| {
+        let mut mentions = mentions
+            .into_iter()
+            .map(|v| v.into_iter().map(String::from).collect::<Mention>());
+        Stage {
+            mention: mentions.next().unwrap_or_default(),
+            fanout: mentions.collect(),
+        }
+    });
+    let Stage { mention, fanout } = stages.next().unwrap_or_default();
+    let pipe = stages.collect();
 
-    let trimmed = content.trim();
-    if content != trimmed {
-        content = trimmed.to_string();
+    if !ends_quoted {
+        content.truncate(content.trim_end().len());
     }
 
     Ok(Query {
         mention,
+        fanout,
+        pipe,
         content,
         scope,
+        spans,
     })
 }
 
 #[cfg(test)]
 mod test {
-    use winnow::Parser;
-    use super::Segment;
+    use winnow::{error::{ContextError, ErrMode}, ModalResult, Parser};
+    use super::{ParseOptions, Segment};
+
+    fn with_default<'i, O>(
+        parser: impl Fn(&mut &'i str, &ParseOptions) -> ModalResult<O>,
+    ) -> impl Parser<&'i str, O, ErrMode<ContextError>> {
+        move |input: &mut &'i str| parser(input, &ParseOptions::default())
+    }
 
     #[test]
     fn test_parse_identifier() {
@@ -136,8 +403,14 @@ mod test {
     #[test]
     fn test_parse_mention() {
         let input = "mention.hi.you";
-        let result = super::parse_mention.parse(input).unwrap();
+        let result = with_default(super::parse_mention).parse(input).unwrap();
         assert_eq!(result, Segment::Mention(vec!["mention", "hi", "you"]));
+
+        let input = "gh.rust-lang。rust.1.0";
+        let result = with_default(super::parse_mention).parse(input).unwrap();
+        assert_eq!(result, Segment::Mention(vec!["gh", "rust-lang", "rust", "1", "0"]));
+
+        assert!(with_default(super::parse_mention).parse("1.0").is_err());
     }
 
     #[test]
@@ -147,9 +420,174 @@ mod test {
         let reference = Query {
             mention: vec!["mention".to_string(), "hello".to_string()].into(),
             content: "content hi there".to_string(),
-            scope: None,
+            ..Default::default()
         };
-        let target = super::parse_query.parse(input).unwrap();
+        let target = with_default(super::parse_query).parse(input).unwrap();
         assert_eq!(target, reference);
     }
+
+    #[test]
+    fn test_parse_scope() {
+        for (input, key, value) in [
+            ("week", "", "week"),
+            ("lang=zh", "lang", "zh"),
+            ("时间＝周", "时间", "周"),
+            ("a=b=c", "a", "b=c"),
+            ("=zh", "", "zh"),
+            ("=a=b", "", "a=b"),
+            ("a+b=c", "", "a+b=c"),
+            ("", "", ""),
+        ] {
+            let result = with_default(super::parse_scope).parse(input).unwrap();
+            assert_eq!(result, Segment::Scope(key, value), "input: {input}");
+        }
+    }
+
+    #[test]
+    fn test_parse_query_scopes() {
+        use super::Query;
+        let input = "@g !time=week rust !lang=zh !new async";
+        let reference = Query {
+            mention: vec!["g".to_string()].into(),
+            content: "rust async".to_string(),
+            scope: [("time", "week"), ("lang", "zh"), ("", "new")]
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        let target = with_default(super::parse_query).parse(input).unwrap();
+        assert_eq!(target, reference);
+        assert!(target.scope.keys().eq(["time", "lang", ""]));
+
+        assert!(with_default(super::parse_query).parse("@g !old rust !new").is_err());
+        assert!(with_default(super::parse_query).parse("!lang=en !lang=zh").is_err());
+    }
+
+    #[test]
+    fn test_parse_query_fanout() {
+        use super::Query;
+        let input = "@g @bing.cn rust async @ddg";
+        let reference = Query {
+            mention: vec!["g".to_string()].into(),
+            fanout: vec![
+                vec!["bing".to_string(), "cn".to_string()].into(),
+                vec!["ddg".to_string()].into(),
+            ],
+            content: "rust async".to_string(),
+            ..Default::default()
+        };
+        let target = with_default(super::parse_query).parse(input).unwrap();
+        assert_eq!(target, reference);
+    }
+
+    #[test]
+    fn test_parse_query_pipe() {
+        use super::{Query, Stage};
+        let input = "@translate.en | @g 你好 ｜ @qr a|b \\|";
+        let reference = Query {
+            mention: vec!["translate".to_string(), "en".to_string()].into(),
+            pipe: vec![
+                Stage {
+                    mention: vec!["g".to_string()].into(),
+                    ..Default::default()
+                },
+                Stage {
+                    mention: vec!["qr".to_string()].into(),
+                    ..Default::default()
+                },
+            ],
+            content: "你好 a|b |".to_string(),
+            ..Default::default()
+        };
+        let target = with_default(super::parse_query).parse(input).unwrap();
+        assert_eq!(target, reference);
+    }
+
+    #[test]
+    fn test_parse_query_custom_sigils() {
+        use super::Query;
+        let options = ParseOptions {
+            mention: vec!['/', ':'],
+            scope: vec!['#'],
+            ..Default::default()
+        };
+        assert_eq!(options.validate(), Ok(()));
+
+        let input = ":docs.serde #lang=en @user \\#tag";
+        let reference = Query {
+            mention: vec!["docs".to_string(), "serde".to_string()].into(),
+            content: "@user #tag".to_string(),
+            scope: [("lang".to_string(), "en".to_string())].into(),
+            ..Default::default()
+        };
+        let target = (|input: &mut &str| super::parse_query(input, &options))
+            .parse(input)
+            .unwrap();
+        assert_eq!(target, reference);
+
+        let with = |set: fn(&mut ParseOptions)| {
+            let mut options = ParseOptions::default();
+            set(&mut options);
+            options
+        };
+        for (conflicting, err) in [
+            (with(|options| options.scope = vec!['@']), "`@` is a sigil for both `mention` and `scope`."),
+            (with(|options| options.path_sep = vec!['!']), "`!` is a sigil for both `path_sep` and `scope`."),
+            (with(|options| options.scope_kv_sep = vec!['|']), "`|` is a sigil for both `scope_kv_sep` and `pipe`."),
+            (with(|options| options.path_sep = vec!['"']), "`\"` cannot be used as a sigil for `path_sep`."),
+            (with(|options| options.mention = vec!['a']), "`a` cannot be used as a sigil for `mention`."),
+            (with(|options| options.scope = vec!['7']), "`7` cannot be used as a sigil for `scope`."),
+            (with(|options| options.path_sep = vec!['-']), "`-` cannot be used as a sigil for `path_sep`."),
+            (with(|options| options.scope_kv_sep = vec!['_']), "`_` cannot be used as a sigil for `scope_kv_sep`."),
+            (with(|options| options.pipe = vec![]), "No sigil is given for `pipe`."),
+        ] {
+            assert_eq!(conflicting.validate().err().as_deref(), Some(err));
+        }
+    }
+
+    #[test]
+    fn test_parse_quoted() {
+        let input = r#""hello @world \"escaped\" \\ C:\Users""#;
+        let result = with_default(super::parse_sigil).parse(input).unwrap();
+        assert_eq!(
+            result,
+            Segment::Quoted(r#"hello @world "escaped" \ C:\Users"#.into())
+        );
+
+        assert!(with_default(super::parse_sigil).parse(r#""unterminated"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_escaped() {
+        for (input, expected) in [(r"\@", '@'), (r"\！", '！'), (r#"\""#, '"'), (r"\\", '\\')] {
+            let result = with_default(super::parse_sigil).parse(input).unwrap();
+            assert_eq!(result, Segment::Escaped(expected));
+        }
+
+        assert!(with_default(super::parse_sigil).parse(r"\a").is_err());
+    }
+
+    #[test]
+    fn test_parse_query_literal() {
+        use super::Query;
+        for (input, mention, content) in [
+            (r"@g \@rustlang", vec!["g"], "@rustlang"),
+            (r"\!important stuff @g", vec!["g"], "!important stuff"),
+            (r#"@g "!bang  @at" end"#, vec!["g"], "!bang  @at end"),
+            (r#""  padded  ""#, vec![], "  padded  "),
+            (r"C:\Users \\", vec![], r"C:\Users \"),
+            ("user@example.com", vec![], "user@example.com"),
+        ] {
+            let reference = Query {
+                mention: mention.into_iter().map(String::from).collect(),
+                content: content.to_string(),
+                ..Default::default()
+            };
+            let target = with_default(super::parse_query).parse(input).unwrap();
+            assert_eq!(target, reference, "input: {input}");
+        }
+
+        assert!(with_default(super::parse_query).parse(r#"@g "unterminated"#).is_err());
+    }
 }
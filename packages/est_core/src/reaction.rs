@@ -4,8 +4,44 @@ use url::Url;
 #[non_exhaustive]
 #[derive(Error, Debug)]
 pub enum AcceptanceErr {
-    #[error("No such specified engine.")]
-    NoEngine,
+    /// The mentioned id matches no engine, optionally with similar ids that may have been meant.
+    #[error("No engine is named `{id}`.{}", did_you_mean(suggestions))]
+    UnknownEngine { id: String, suggestions: Vec<String> },
+
+    /// The mentioned id is a prefix shared by the ids of several engines.
+    #[error("`{id}` may refer to any of {}.", candidates.join(" / "))]
+    AmbiguousEngine { id: String, candidates: Vec<String> },
+
+    /// The segments after the engine id do not fit the parameters of the engine.
+    #[error("Bad arguments for `{id}`: {reason}.")]
+    BadArguments { id: String, reason: String },
+
+    /// The engine declines the query, which other engines may still take.
+    #[error("`{id}` rejected the query: {reason}.")]
+    Rejected { id: String, reason: String },
+
+    /// The engine cannot take the query, and no other engine should be tried instead.
+    #[error("`{id}` failed: {reason}.")]
+    Failed { id: String, reason: String },
+}
+
+impl AcceptanceErr {
+    /// Whether another engine may be tried instead, as by a chain.
+    /// An unknown or ambiguous mention is a mistake in the query, and is not recoverable.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(
+            self,
+            Self::BadArguments { .. } | Self::Rejected { .. }
+        )
+    }
+}
+
+fn did_you_mean(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        String::new()
+    } else {
+        format!(" Did you mean {}?", suggestions.join(" / "))
+    }
 }
 
 #[non_exhaustive]
@@ -13,6 +49,9 @@ pub enum AcceptanceErr {
 pub enum ReactionVerb {
     Navigate(Navigate),
     Forward(Forward),
+    FanOut(FanOut),
+    Text(Text),
+    Answer(Answer),
 }
 
 #[non_exhaustive]
@@ -32,6 +71,28 @@ pub enum ReactionErr {
 
     #[error("Too many forwards before deciding on an engine to process the query.")]
     TooManyForward,
+
+    #[error("Reactions cannot be combined: {0}")]
+    Incompatible(String),
+
+    /// The script of an engine went wrong, such as by exceeding its limits.
+    #[error("Script of `{id}` failed: {message}")]
+    Script { id: String, message: String },
+
+    /// The plugin of an engine failed or rejected the query.
+    #[error("Plugin `{id}` failed: {message}")]
+    Plugin { id: String, message: String },
+}
+
+impl ReactionErr {
+    /// Whether another engine may be tried instead, as by a chain.
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            Self::Nothing => true,
+            Self::NotAccepted(err) => err.is_recoverable(),
+            _ => false,
+        }
+    }
 }
 
 pub type Reaction = Result<ReactionVerb, ReactionErr>;
@@ -65,6 +126,140 @@ impl Navigate {
     }
 }
 
+/// Plain text produced by an engine, which can be piped into another engine as its content.
+#[derive(Clone, Debug)]
+pub struct Text {
+    text: String,
+}
+
+impl From<Text> for ReactionVerb {
+    fn from(text: Text) -> Self {
+        ReactionVerb::Text(text)
+    }
+}
+
+impl Text {
+    pub fn new(text: impl Into<String>) -> Self {
+        Text { text: text.into() }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// An answer shown to the user in place of a navigation, such as the result of a calculation.
+#[derive(Clone, Debug)]
+pub struct Answer {
+    text: String,
+    html: Option<String>,
+}
+
+impl From<Answer> for ReactionVerb {
+    fn from(answer: Answer) -> Self {
+        ReactionVerb::Answer(answer)
+    }
+}
+
+impl Answer {
+    pub fn new(text: impl Into<String>) -> Self {
+        Answer {
+            text: text.into(),
+            html: None,
+        }
+    }
+
+    /// Show the answer as an HTML fragment, which is trusted as is, with the text as its plain version.
+    /// Only builtin engines can set it, as they escape what they embed; others answer with text alone.
+    #[cfg_attr(not(feature = "bookmarks"), allow(dead_code))]
+    pub(crate) fn with_html(mut self, html: impl Into<String>) -> Self {
+        self.html = Some(html.into());
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn html(&self) -> Option<&str> {
+        self.html.as_deref()
+    }
+}
+
+/// Escape text to embed in HTML or XML, such as in the HTML of an answer.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl ReactionVerb {
+    /// Turn the reaction into the content of the next stage of a pipeline.
+    /// A navigation is piped as its URL, and an answer as its text.
+    pub(crate) fn into_piped_content(self) -> Result<String, ReactionErr> {
+        match self {
+            ReactionVerb::Text(text) => Ok(text.text),
+            ReactionVerb::Answer(answer) => Ok(answer.text),
+            ReactionVerb::Navigate(nav) => Ok(nav.url.into()),
+            ReactionVerb::FanOut(_) => Err(ReactionErr::Incompatible(
+                "a query sent to several engines cannot be piped".to_string(),
+            )),
+            ReactionVerb::Forward(_) => Err(ReactionErr::Panic(
+                "an unresolved forward is piped".to_string(),
+            )),
+        }
+    }
+}
+
+/// Navigate to several destinations at once, one for each engine mentioned in a query.
+#[derive(Clone, Debug)]
+pub struct FanOut {
+    navigations: Vec<Navigate>,
+    /// Mentions that failed, with their errors.
+    failures: Vec<(String, String)>,
+}
+
+impl From<FanOut> for ReactionVerb {
+    fn from(fan_out: FanOut) -> Self {
+        ReactionVerb::FanOut(fan_out)
+    }
+}
+
+impl FromIterator<Navigate> for FanOut {
+    fn from_iter<T: IntoIterator<Item = Navigate>>(iter: T) -> Self {
+        FanOut {
+            navigations: iter.into_iter().collect(),
+            failures: Vec::new(),
+        }
+    }
+}
+
+impl FanOut {
+    pub fn navigations(&self) -> &[Navigate] {
+        &self.navigations
+    }
+
+    /// List the mentions that failed, with their errors, along with the navigations.
+    pub fn with_failures(mut self, failures: Vec<(String, String)>) -> Self {
+        self.failures = failures;
+        self
+    }
+
+    /// The mentions that failed, with their errors.
+    pub fn failures(&self) -> &[(String, String)] {
+        &self.failures
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Forward {
     /// Prepend a new mention segment to the query, and optionally drop the first-n mention segments.
@@ -76,6 +271,13 @@ pub enum Forward {
     /// - `Mention(d, 3)` -> `@d`
     /// - `Mention(d, 4)` -> `@d`
     Mention(String, usize),
+
+    /// Forward like [`Forward::Mention`], and replace the content of the query as well.
+    Rewrite(String, usize, String),
+
+    /// Forward like [`Forward::Mention`] to each engine in turn, taking the first reaction
+    ///   that is not a recoverable error, see [`ReactionErr::is_recoverable`].
+    Fallback(Vec<String>, usize),
 }
 
 impl From<Forward> for ReactionVerb {
@@ -0,0 +1,362 @@
+//! Compiled URL templates filled with parts of a query.
+//!
+//! A template is literal text with placeholders in braces:
+//! - `{content}` is the content of the query, and `{}` is a shorthand for it.
+//! - `{scope}` is the unkeyed scope, and `{scope.key}` is the scope with the key.
+//!   `{!}` and `{!key}` are shorthands for them.
+//! - `{mention.n}` is the n-th segment of the mention, `{mention.0}` being the engine itself.
+//! - `{arg.name}` is the argument bound to a parameter of the engine, see [`crate::arguments`].
+//!
+//! Values are percent-encoded according to where they appear in the URL.
+//! Before `?` they are encoded as a path segment, and after it as a query component.
+//! Filters after `|` change that, and they are applied from left to right:
+//! - `raw` inserts the value as is.
+//! - `path` encodes the value as a path, keeping `/`.
+//! - `form` encodes the value as a form value, with spaces as `+`.
+//! - `lower` turns the value into lowercase.
+//! - `slug` turns the value into a lowercase, dash-separated slug.
+//!
+//! Literal braces are written as `{{` and `}}`.
+use crate::{Query, arguments::Arguments};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::{borrow::Cow, str::FromStr};
+use thiserror::Error;
+
+/// Characters encoded in a query component, leaving only the unreserved ones.
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Characters encoded in a path segment, which may also contain sub-delimiters, `:` and `@`.
+const PATH_SEGMENT: &AsciiSet = &COMPONENT
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b'+')
+    .remove(b',')
+    .remove(b';')
+    .remove(b'=')
+    .remove(b':')
+    .remove(b'@');
+
+/// Characters encoded in a path of several segments.
+const PATH: &AsciiSet = &PATH_SEGMENT.remove(b'/');
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TemplateError {
+    #[error("Unclosed placeholder starting at byte {0}.")]
+    Unclosed(usize),
+    #[error("Unmatched `}}` at byte {0}, write `}}}}` for a literal brace.")]
+    Unmatched(usize),
+    #[error("Unknown placeholder `{0}`.")]
+    UnknownValue(String),
+    #[error("Unknown filter `{0}`.")]
+    UnknownFilter(String),
+}
+
+/// Where a placeholder appears in the URL, which decides how it is encoded by default.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Context {
+    Path,
+    Query,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Value {
+    Content,
+    Scope(String),
+    Mention(usize),
+    Arg(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Filter {
+    Raw,
+    Path,
+    Form,
+    Lower,
+    Slug,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Raw,
+    Set(&'static AsciiSet),
+    Form,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Placeholder {
+    value: Value,
+    filters: Vec<Filter>,
+    context: Context,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+impl FromStr for Value {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, key) = match s.split_once('.') {
+            Some((name, key)) => (name, Some(key)),
+            None => (s, None),
+        };
+        match (name, key) {
+            ("" | "content", None) => Ok(Value::Content),
+            ("scope", key) => Ok(Value::Scope(key.unwrap_or(Query::SCOPE_UNKEYED).to_string())),
+            ("mention", Some(index)) => index
+                .parse()
+                .map(Value::Mention)
+                .map_err(|_| TemplateError::UnknownValue(s.to_string())),
+            ("arg", Some(name)) if !name.is_empty() => Ok(Value::Arg(name.to_string())),
+            _ => match s.strip_prefix('!') {
+                Some(key) => Ok(Value::Scope(key.to_string())),
+                None => Err(TemplateError::UnknownValue(s.to_string())),
+            },
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "raw" => Ok(Filter::Raw),
+            "path" => Ok(Filter::Path),
+            "form" => Ok(Filter::Form),
+            "lower" => Ok(Filter::Lower),
+            "slug" => Ok(Filter::Slug),
+            _ => Err(TemplateError::UnknownFilter(s.to_string())),
+        }
+    }
+}
+
+fn slugify(value: &str) -> String {
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+impl Placeholder {
+    fn parse(inner: &str, context: Context) -> Result<Self, TemplateError> {
+        let mut parts = inner.split('|');
+        let value = parts.next().unwrap_or_default().trim().parse()?;
+        let filters = parts.map(str::parse).collect::<Result<_, _>>()?;
+        Ok(Self {
+            value,
+            filters,
+            context,
+        })
+    }
+
+    fn render(&self, query: &Query, arguments: &Arguments, out: &mut String) {
+        let mut value: Cow<str> = match &self.value {
+            Value::Content => query.content().into(),
+            Value::Scope(key) => query.scope(key).unwrap_or_default().into(),
+            Value::Mention(index) => query
+                .mention
+                .get(*index)
+                .map(String::as_str)
+                .unwrap_or_default()
+                .into(),
+            Value::Arg(name) => arguments.get(name.as_str()).map(String::as_str).unwrap_or_default().into(),
+        };
+
+        let mut encoding = Encoding::Set(match self.context {
+            Context::Path => PATH_SEGMENT,
+            Context::Query => COMPONENT,
+        });
+        for filter in &self.filters {
+            match filter {
+                Filter::Raw => encoding = Encoding::Raw,
+                Filter::Path => encoding = Encoding::Set(PATH),
+                Filter::Form => encoding = Encoding::Form,
+                Filter::Lower => value = value.to_lowercase().into(),
+                Filter::Slug => value = slugify(&value).into(),
+            }
+        }
+
+        match encoding {
+            Encoding::Raw => out.push_str(&value),
+            Encoding::Set(set) => out.extend(utf8_percent_encode(&value, set)),
+            Encoding::Form => out.extend(url::form_urlencoded::byte_serialize(value.as_bytes())),
+        }
+    }
+}
+
+impl FromStr for Template {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut context = Context::Path;
+        let mut rest = s;
+
+        while let Some(i) = rest.find(['{', '}']) {
+            let offset = s.len() - rest.len() + i;
+            literal.push_str(&rest[..i]);
+            let (brace, after) = rest[i..].split_at(1);
+
+            if after.starts_with(brace) {
+                literal.push_str(brace);
+                rest = &after[1..];
+                continue;
+            }
+            if brace == "}" {
+                return Err(TemplateError::Unmatched(offset));
+            }
+
+            if literal.contains(['?', '#']) {
+                context = Context::Query;
+            }
+            let end = after.find('}').ok_or(TemplateError::Unclosed(offset))?;
+            if !literal.is_empty() {
+                pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+            }
+            pieces.push(Piece::Placeholder(Placeholder::parse(&after[..end], context)?));
+            rest = &after[end + 1..];
+        }
+
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+
+        Ok(Self { pieces })
+    }
+}
+
+impl Template {
+    /// Fill the template with the query and the arguments bound from its mention.
+    pub fn render(&self, query: &Query, arguments: &Arguments) -> String {
+        let mut out = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Literal(literal) => out.push_str(literal),
+                Piece::Placeholder(placeholder) => placeholder.render(query, arguments, &mut out),
+            }
+        }
+        out
+    }
+
+    /// Iterate over the values the template reads.
+    pub(crate) fn values(&self) -> impl Iterator<Item = &Value> {
+        self.pieces.iter().filter_map(|piece| match piece {
+            Piece::Placeholder(placeholder) => Some(&placeholder.value),
+            Piece::Literal(_) => None,
+        })
+    }
+
+    /// Iterate over the keys of the scopes the template reads.
+    pub fn scope_keys(&self) -> impl Iterator<Item = &str> {
+        self.values().filter_map(|value| match value {
+            Value::Scope(key) => Some(key.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Iterate over the names of the arguments the template reads.
+    pub(crate) fn arg_names(&self) -> impl Iterator<Item = &str> {
+        self.values().filter_map(|value| match value {
+            Value::Arg(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Template, TemplateError};
+    use crate::{Query, arguments::Arguments};
+
+    fn render(template: &str, query: &str) -> String {
+        let template: Template = template.parse().unwrap();
+        template.render(&query.parse::<Query>().unwrap(), &Arguments::new())
+    }
+
+    #[test]
+    fn test_render_encoding() {
+        assert_eq!(
+            render("https://example.com/search?q={}", "c++ & rust"),
+            "https://example.com/search?q=c%2B%2B%20%26%20rust"
+        );
+        assert_eq!(
+            render("https://example.com/search?q={content|form}", "c++ & rust"),
+            "https://example.com/search?q=c%2B%2B+%26+rust"
+        );
+        assert_eq!(
+            render("https://example.com/wiki/{content}", "a+b/c d"),
+            "https://example.com/wiki/a+b%2Fc%20d"
+        );
+        assert_eq!(
+            render("https://example.com/{content|path}", "a+b/c d"),
+            "https://example.com/a+b/c%20d"
+        );
+        assert_eq!(render("{content|raw}", "https://example.com/?q=a b"), "https://example.com/?q=a b");
+        assert_eq!(render("https://example.com/#{}", "a#b"), "https://example.com/#a%23b");
+        assert_eq!(render("https://例子.com/{}?q={}", "你好"), "https://例子.com/%E4%BD%A0%E5%A5%BD?q=%E4%BD%A0%E5%A5%BD");
+    }
+
+    #[test]
+    fn test_render_values() {
+        let template = "https://{mention.1}.example.com/{content|slug}?lang={scope.lang|lower}&t={!}&x={mention.5}";
+        assert_eq!(
+            render(template, "@wiki.en Hello, World! !lang=ZH !week"),
+            "https://en.example.com/hello-world?lang=zh&t=week&x="
+        );
+        assert_eq!(
+            render("https://example.com/?q={}&lang={!lang}&l={{literal}}", "rust !lang=zh {x}"),
+            "https://example.com/?q=rust%20%7Bx%7D&lang=zh&l={literal}"
+        );
+
+        let template: Template = "https://docs.rs/{arg.crate}/{arg.version}/?search={}".parse().unwrap();
+        let arguments = Arguments::from([("crate", "serde".to_string()), ("version", "1.0".to_string())]);
+        assert_eq!(
+            template.render(&"@docs.serde.1.0 Deserialize".parse().unwrap(), &arguments),
+            "https://docs.rs/serde/1.0/?search=Deserialize"
+        );
+    }
+
+    #[test]
+    fn test_scope_is_not_resubstituted() {
+        assert_eq!(
+            render("https://example.com/?s={!}&q={}", "rust !{}"),
+            "https://example.com/?s=%7B%7D&q=rust"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (template, err) in [
+            ("https://example.com/{content", TemplateError::Unclosed(20)),
+            ("https://example.com/}", TemplateError::Unmatched(20)),
+            ("{contents}", TemplateError::UnknownValue("contents".to_string())),
+            ("{mention.x}", TemplateError::UnknownValue("mention.x".to_string())),
+            ("{arg.}", TemplateError::UnknownValue("arg.".to_string())),
+            ("{content|upper}", TemplateError::UnknownFilter("upper".to_string())),
+        ] {
+            assert_eq!(template.parse::<Template>(), Err(err), "template: {template}");
+        }
+    }
+}
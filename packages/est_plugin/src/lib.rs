@@ -0,0 +1,179 @@
+//! Guest-side SDK for `est` engines written as WebAssembly plugins.
+//!
+//! The messages exchanged with the host are defined here, see `ABI.md` for the full ABI.
+//! A plugin is a `cdylib` that implements a function from [`Request`] to [`Response`]
+//!   and exports it with [`export!`]:
+//!
+//! ```
+//! use est_plugin::{Request, Response};
+//!
+//! fn react(request: Request) -> Response {
+//!     match request.content.strip_prefix('#') {
+//!         Some(number) => Response::forward_with("issue", number),
+//!         None => Response::Navigate(format!("https://example.com/?q={}", request.content)),
+//!     }
+//! }
+//!
+//! est_plugin::export!(react);
+//!
+//! let request = Request { content: "#42".to_string(), ..Default::default() };
+//! assert_eq!(react(request), Response::forward_with("issue", "42"));
+//! ```
+//!
+//! The exports only exist when compiling for `wasm32`,
+//!   so the same crate can be tested natively as above, or through [`handle`] with raw JSON.
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The version of the ABI implemented by this crate, returned by the `est_abi_version` export.
+pub const ABI_VERSION: i32 = 1;
+
+/// A query sent to the plugin.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Request {
+    /// Segments of the mention, the first one being the id the plugin is registered under.
+    pub mention: Vec<String>,
+    pub content: String,
+    /// Scopes by key, the unkeyed one under `""`.
+    pub scope: BTreeMap<String, String>,
+    /// The `config` table of the engine in the compose file, or `null`.
+    pub config: serde_json::Value,
+}
+
+/// What the plugin decides to do with the query.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Response {
+    /// Navigate to the URL.
+    Navigate(String),
+    /// Send the query to another engine, optionally with new content.
+    Forward {
+        to: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        content: Option<String>,
+    },
+    /// Answer with plain text.
+    Text(String),
+    /// Reject the query with a reason, so that a chain goes on with its next engine.
+    Reject(String),
+    /// Fail the query with a message, so that no other engine is tried.
+    Error(String),
+}
+
+impl Response {
+    pub fn forward(to: impl Into<String>) -> Self {
+        Self::Forward {
+            to: to.into(),
+            content: None,
+        }
+    }
+
+    pub fn forward_with(to: impl Into<String>, content: impl Into<String>) -> Self {
+        Self::Forward {
+            to: to.into(),
+            content: Some(content.into()),
+        }
+    }
+}
+
+/// Decode a JSON request, react to it, and encode the response.
+/// A request that cannot be decoded is answered with [`Response::Error`].
+pub fn handle(request: &[u8], react: impl FnOnce(Request) -> Response) -> Vec<u8> {
+    let response = match serde_json::from_slice(request) {
+        Ok(request) => react(request),
+        Err(err) => Response::Error(format!("Invalid request: {err}")),
+    };
+    serde_json::to_vec(&response).expect("Responses are always serializable.")
+}
+
+/// Write a message to the log of the host, if it allows the plugin to.
+pub fn log(message: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        #[link(wasm_import_module = "est")]
+        unsafe extern "C" {
+            #[link_name = "log"]
+            fn host_log(ptr: i32, len: i32);
+        }
+        unsafe { host_log(message.as_ptr() as usize as i32, message.len() as i32) }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    eprintln!("{message}");
+}
+
+/// Implementation of the exports, used by [`export!`].
+#[doc(hidden)]
+#[cfg(target_arch = "wasm32")]
+pub mod guest {
+    pub fn alloc(len: i32) -> i32 {
+        let mut buffer = Vec::<u8>::with_capacity(len as usize);
+        let ptr = buffer.as_mut_ptr();
+        std::mem::forget(buffer);
+        ptr as usize as i32
+    }
+
+    /// # Safety
+    /// `ptr` and `len` must describe a buffer returned by [`alloc`] and filled by the host.
+    pub unsafe fn react(ptr: i32, len: i32, react: impl FnOnce(super::Request) -> super::Response) -> i64 {
+        let request = unsafe { Vec::from_raw_parts(ptr as usize as *mut u8, len as usize, len as usize) };
+        let response = super::handle(&request, react).leak();
+        ((response.as_ptr() as usize as i64) << 32) | response.len() as i64
+    }
+}
+
+/// Export a function from [`Request`] to [`Response`] as the plugin.
+#[macro_export]
+macro_rules! export {
+    ($react:path) => {
+        #[cfg(target_arch = "wasm32")]
+        #[unsafe(no_mangle)]
+        pub extern "C" fn est_abi_version() -> i32 {
+            $crate::ABI_VERSION
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        #[unsafe(no_mangle)]
+        pub extern "C" fn est_alloc(len: i32) -> i32 {
+            $crate::guest::alloc(len)
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn est_react(ptr: i32, len: i32) -> i64 {
+            unsafe { $crate::guest::react(ptr, len, $react) }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Request, Response, handle};
+
+    #[test]
+    fn test_handle() {
+        let request = br#"{"mention": ["echo"], "content": "rust", "scope": {"lang": "en"}}"#;
+        let response = handle(request, |request| {
+            Response::Text(format!("{} {}", request.content, request.scope["lang"]))
+        });
+        assert_eq!(response, br#"{"text":"rust en"}"#);
+
+        let response = handle(b"not json", |_| unreachable!());
+        assert!(matches!(serde_json::from_slice(&response), Ok(Response::Error(_))));
+    }
+
+    #[test]
+    fn test_response_json() {
+        for (response, json) in [
+            (Response::Navigate("https://example.com/".to_string()), r#"{"navigate":"https://example.com/"}"#),
+            (Response::forward("google"), r#"{"forward":{"to":"google"}}"#),
+            (Response::forward_with("issue", "42"), r#"{"forward":{"to":"issue","content":"42"}}"#),
+            (Response::Reject("no".to_string()), r#"{"reject":"no"}"#),
+            (Response::Error("no".to_string()), r#"{"error":"no"}"#),
+        ] {
+            assert_eq!(serde_json::to_string(&response).unwrap(), json);
+            assert_eq!(serde_json::from_str::<Response>(json).unwrap(), response);
+        }
+        assert_eq!(serde_json::from_str::<Request>("{}").unwrap(), Request::default());
+    }
+}
@@ -0,0 +1,71 @@
+//! Commands run instead of the server when arguments are given.
+//!
+//! The `import-*` commands print the engines they import as `[[engines]]` to paste into `config.toml`.
+use est_core::import::{self, ImportError, Imported};
+use std::{path::Path, process::ExitCode};
+
+const USAGE: &str = "\
+Usage: est_server [COMMAND]
+
+Without a command, serve with the config.toml of the working directory.
+
+Commands:
+  import-bangs <path>          Print the bangs of a DuckDuckGo or Kagi file as engines of config.toml
+  import-opensearch <path>...  Print the engines of OpenSearch description files
+  import-firefox <profile>     Print the search engines and bookmark keywords of a Firefox profile directory
+  import-chrome <path>         Print the search engines of the `Web Data` file of a Chrome profile
+  help                         Print this message";
+
+pub fn run(args: &[String]) -> ExitCode {
+    match args {
+        [command, path] if command == "import-bangs" => {
+            print_imported(import::bangs::load(Path::new(path)))
+        }
+        [command, paths @ ..] if command == "import-opensearch" && !paths.is_empty() => {
+            print_imported(
+                paths
+                    .iter()
+                    .try_fold(Imported::default(), |mut imported, path| {
+                        imported.extend(import::opensearch::load(Path::new(path))?);
+                        Ok(imported)
+                    }),
+            )
+        }
+        [command, path] if command == "import-firefox" => {
+            print_imported(import::firefox::load_profile(Path::new(path)))
+        }
+        [command, path] if command == "import-chrome" => {
+            print_imported(import::chrome::load(Path::new(path)))
+        }
+        [command] if command == "help" || command == "--help" || command == "-h" => {
+            println!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn print_imported(imported: Result<Imported, ImportError>) -> ExitCode {
+    let imported = match imported {
+        Ok(imported) => imported,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match toml::to_string(&imported) {
+        Ok(toml) => {
+            print!("{toml}");
+            eprintln!("Imported {} engines.", imported.len());
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("Cannot write the engines as TOML: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
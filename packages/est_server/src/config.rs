@@ -1,5 +1,24 @@
+use serde::Deserialize;
 use std::{env, path::PathBuf};
 
+/// Options of the server itself, as the `[server]` table of config.toml.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ServerOptions {
+    /// The url est is publicly reachable at, such as `https://est.example.com`.
+    /// Without it, urls are made from the `Host` of each request.
+    pub base_url: Option<String>,
+    /// The url template of search suggestions offered by `/search.xml`, with `{searchTerms}` for the query.
+    /// Without it, browsers are offered no suggestions.
+    pub suggestions: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ServerConfig {
+    #[serde(default)]
+    server: ServerOptions,
+}
+
 fn locate_config_file() -> Option<PathBuf> {
     let path_config_home = env::var("XDG_CONFIG_HOME")
         .map(|home| {
@@ -24,7 +43,7 @@ fn locate_config_file() -> Option<PathBuf> {
         .find(|f| f.exists())
 }
 
-pub fn build() -> est_core::Instance {
+pub fn build() -> (est_core::Instance, ServerOptions) {
     let path = locate_config_file().expect("Cannot find config.toml");
     let config = std::fs::read_to_string(&path).expect("Cannot read config.toml");
 
@@ -32,5 +51,9 @@ pub fn build() -> est_core::Instance {
         panic!("Failed to parse config file: {}", err);
     });
 
-    est_core::Instance::from(compose)
+    let server = toml::from_str::<ServerConfig>(&config).unwrap_or_else(|err| {
+        panic!("Failed to parse config file: {}", err);
+    });
+
+    (est_core::Instance::from(compose), server.server)
 }
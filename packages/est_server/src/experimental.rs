@@ -19,10 +19,11 @@ async fn description(
     State(state): State<Arc<AppState>>,
     Path(id): Path<String>,
 ) -> Json<Value> {
-    let description = state.instance.read().await.describe(&id);
+    let instance = state.instance.read().await;
     Json(json!({
         "id": id,
-        "description": description,
+        "description": instance.describe(&id),
+        "icon": instance.icon(&id),
     }))
 }
 
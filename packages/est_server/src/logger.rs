@@ -0,0 +1,24 @@
+//! A logger printing records to stderr, such as the messages of plugins.
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Info
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{} {}: {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+pub fn init() {
+    log::set_logger(&StderrLogger)
+        .map(|()| log::set_max_level(LevelFilter::Info))
+        .expect("Cannot set the logger");
+}
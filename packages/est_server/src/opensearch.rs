@@ -0,0 +1,152 @@
+//! OpenSearch descriptions, so that est or one of its engines can be added to a browser as a search engine.
+//!
+//! `/search.xml` searches with est as a whole, and `/opensearch/{id}.xml` with the engine `id`,
+//!   by mentioning it before the search terms.
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use est_core::reaction::escape_html;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+use crate::AppState;
+
+/// Characters encoded in the query string of the url template.
+const QUERY: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// The public base url of est, from the config or else from the `Host` of the request.
+fn base_url(configured: Option<&str>, headers: &HeaderMap) -> String {
+    if let Some(base_url) = configured {
+        return base_url.trim_end_matches('/').to_string();
+    }
+    let host = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+        .unwrap_or("localhost");
+    format!("http://{host}")
+}
+
+struct Description<'d> {
+    short_name: &'d str,
+    description: &'d str,
+    /// Prepended to the search terms, such as the mention of an engine.
+    prefix: String,
+    icon: Option<&'d str>,
+    suggestions: Option<&'d str>,
+}
+
+impl Description<'_> {
+    fn render(&self, base_url: &str) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <OpenSearchDescription xmlns=\"http://a9.com/-/spec/opensearch/1.1/\">\n",
+        );
+        xml.push_str(&format!("  <ShortName>{}</ShortName>\n", escape_html(self.short_name)));
+        xml.push_str(&format!("  <Description>{}</Description>\n", escape_html(self.description)));
+        xml.push_str("  <InputEncoding>UTF-8</InputEncoding>\n");
+        if let Some(icon) = self.icon {
+            xml.push_str(&format!("  <Image>{}</Image>\n", escape_html(icon)));
+        }
+        let prefix = utf8_percent_encode(&self.prefix, QUERY);
+        xml.push_str(&format!(
+            "  <Url type=\"text/html\" method=\"get\" template=\"{}\"/>\n",
+            escape_html(&format!("{base_url}/search?q={prefix}{{searchTerms}}")),
+        ));
+        if let Some(suggestions) = self.suggestions {
+            xml.push_str(&format!(
+                "  <Url type=\"application/x-suggestions+json\" template=\"{}\"/>\n",
+                escape_html(suggestions),
+            ));
+        }
+        xml.push_str("</OpenSearchDescription>\n");
+        xml
+    }
+}
+
+fn respond(xml: String) -> Response {
+    let mut response = xml.into_response();
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/opensearchdescription+xml; charset=utf-8"),
+    );
+    response
+}
+
+pub async fn est(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    let description = Description {
+        short_name: "Est",
+        description: "Yixuan's Extensible Search Tool",
+        prefix: String::new(),
+        icon: None,
+        suggestions: state.server.suggestions.as_deref(),
+    };
+    respond(description.render(&base_url(state.server.base_url.as_deref(), &headers)))
+}
+
+pub async fn engine(
+    State(state): State<Arc<AppState>>,
+    Path(file): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let id = file.strip_suffix(".xml").ok_or(StatusCode::NOT_FOUND)?;
+    let instance = state.instance.read().await;
+    if !instance.iter_engine_ids().any(|known| known == id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let sigil = instance.parse_options().mention[0];
+    let description = instance.describe(id);
+    let description = Description {
+        short_name: id,
+        description: description.as_deref().unwrap_or("Search through est"),
+        prefix: format!("{sigil}{id} "),
+        icon: instance.icon(id),
+        suggestions: None,
+    };
+    Ok(respond(description.render(&base_url(state.server.base_url.as_deref(), &headers))))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Description, base_url};
+    use axum::http::{HeaderMap, HeaderValue, header};
+
+    #[test]
+    fn test_render() {
+        let description = Description {
+            short_name: "g",
+            description: "Search <Google> & more",
+            prefix: "@g ".to_string(),
+            icon: Some("https://example.com/icon.png?a=1&b=2"),
+            suggestions: None,
+        };
+        let xml = description.render("https://est.example.com");
+        assert!(xml.contains("<ShortName>g</ShortName>"));
+        assert!(xml.contains("<Description>Search &lt;Google&gt; &amp; more</Description>"));
+        assert!(xml.contains("<Image>https://example.com/icon.png?a=1&amp;b=2</Image>"));
+        assert!(xml.contains(r#"template="https://est.example.com/search?q=%40g%20{searchTerms}""#));
+        assert!(!xml.contains("application/x-suggestions+json"));
+
+        let description = Description {
+            suggestions: Some("https://example.com/ac?q={searchTerms}&type=list"),
+            ..description
+        };
+        let xml = description.render("https://est.example.com");
+        assert!(xml.contains(
+            r#"<Url type="application/x-suggestions+json" template="https://example.com/ac?q={searchTerms}&amp;type=list"/>"#
+        ));
+    }
+
+    #[test]
+    fn test_base_url() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(base_url(None, &headers), "http://localhost");
+        headers.insert(header::HOST, HeaderValue::from_static("est.local:3000"));
+        assert_eq!(base_url(None, &headers), "http://est.local:3000");
+        assert_eq!(base_url(Some("https://est.example.com/"), &headers), "https://est.example.com");
+    }
+}
+
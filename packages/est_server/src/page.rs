@@ -0,0 +1,50 @@
+//! Pages rendered for reactions that cannot be expressed as a single redirect.
+
+use axum::response::Html;
+use est_core::reaction::{Answer, FanOut, escape_html};
+
+fn layout(title: &str, body: &str) -> Html<String> {
+    Html(format!(
+        include_str!("./page.html"),
+        title = escape_html(title),
+        body = body,
+    ))
+}
+
+/// List every destination of a fan-out, and offer to open them all in new tabs.
+/// Mentions that failed are listed below with their errors.
+pub fn fan_out(query: &str, fan_out: &FanOut) -> Html<String> {
+    let items: String = fan_out
+        .navigations()
+        .iter()
+        .map(|nav| {
+            let url = escape_html(nav.url().as_str());
+            format!(r#"<li><a href="{url}" target="_blank" rel="noopener">{url}</a></li>"#)
+        })
+        .collect();
+
+    let mut body = format!(
+        r#"<ul id="targets">{items}</ul>
+<button type="button" onclick="document.querySelectorAll('#targets a').forEach(a => window.open(a.href, '_blank', 'noopener'))">Open all</button>"#
+    );
+    if !fan_out.failures().is_empty() {
+        let failures: String = fan_out
+            .failures()
+            .iter()
+            .map(|(mention, err)| format!("<li><code>{}</code>: {}</li>", escape_html(mention), escape_html(err)))
+            .collect();
+        body.push_str(&format!(r#"<ul id="failures">{failures}</ul>"#));
+    }
+
+    layout(query, &body)
+}
+
+/// Show an answer, as the HTML given by the engine, or else as plain text.
+pub fn answer(query: &str, answer: &Answer) -> Html<String> {
+    let body = match answer.html() {
+        Some(html) => html.to_string(),
+        None => format!(r#"<p id="answer">{}</p>"#, escape_html(answer.text())),
+    };
+
+    layout(query, &body)
+}
@@ -6,30 +6,61 @@ use axum::{
 };
 use serde::Deserialize;
 
-use crate::AppState;
+use crate::{page, AppState};
 
 #[derive(Deserialize)]
 pub struct SearchUrlQuery {
     q: String,
 }
 
+/// Render a parse error with a caret line pointing at where the query broke.
+fn render_parse_error(query: &str, err: &est_core::QueryParseError) -> String {
+    let span = err.span();
+    let column = query[..span.start].chars().count();
+    let width = query[span].chars().count().max(1);
+    format!(
+        "Invalid query: {}\n\n{}\n{}{}",
+        err,
+        query,
+        " ".repeat(column),
+        "^".repeat(width),
+    )
+}
+
 pub async fn handle_search(
     State(state): State<Arc<AppState>>,
     Query(url_query): Query<SearchUrlQuery>,
 ) -> Result<Response, (StatusCode, String)> {
     let url_query = url_query.q;
-    let query = url_query
-        .parse::<est_core::Query>()
-        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid query".to_string()))?;
-    // instance.read().await.react()
+    let instance = state.instance.read().await;
+    let query = instance
+        .parse(&url_query)
+        .map_err(|err| (StatusCode::BAD_REQUEST, render_parse_error(&url_query, &err)))?;
 
-    use est_core::{ReactionErr, ReactionVerb};
-    let reaction = state.instance
-        .read()
-        .await
+    use est_core::{AcceptanceErr, ReactionErr, ReactionVerb, reaction::Answer};
+    let reaction = instance
         .react(query)
         .await
         .map_err(|err| match err {
+            ReactionErr::NotAccepted(AcceptanceErr::UnknownEngine { id, suggestions }) => {
+                let sigil = instance.parse_options().mention[0];
+                let mut message = format!("Unknown engine {sigil}{id}.");
+                if !suggestions.is_empty() {
+                    let suggestions: Vec<String> =
+                        suggestions.iter().map(|s| format!("{sigil}{s}")).collect();
+                    message.push_str(&format!(" Did you mean {}?", suggestions.join(" / ")));
+                }
+                (StatusCode::BAD_REQUEST, message)
+            }
+            ReactionErr::NotAccepted(AcceptanceErr::AmbiguousEngine { id, candidates }) => {
+                let sigil = instance.parse_options().mention[0];
+                let candidates: Vec<String> =
+                    candidates.iter().map(|c| format!("{sigil}{c}")).collect();
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("{sigil}{id} is ambiguous. Did you mean {}?", candidates.join(" / ")),
+                )
+            }
             ReactionErr::Panic(err) => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Internal server error: {}", err),
@@ -46,6 +77,10 @@ pub async fn handle_search(
 
     let response = match reaction {
         ReactionVerb::Navigate(nav) => Redirect::to(nav.url().as_str()).into_response(),
+        ReactionVerb::FanOut(fan_out) => page::fan_out(&url_query, &fan_out).into_response(),
+        // Plain text shows like an answer, so that inline results look the same.
+        ReactionVerb::Text(text) => page::answer(&url_query, &Answer::new(text.text())).into_response(),
+        ReactionVerb::Answer(answer) => page::answer(&url_query, &answer).into_response(),
         _ => {
             return Err((
                 StatusCode::INTERNAL_SERVER_ERROR,